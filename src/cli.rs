@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use serenity::all::CommandType;
+use serenity::http::Http;
+use serenity::json::{ self, Value };
+
+use crate::interactions::registry::{ self, SlashCommand };
+use crate::interactions::sync::{ self, Scope };
+
+/// What's printed when the arguments can't be parsed.
+pub const USAGE: &str =
+    "\
+Usage:
+    bot                                          Run the bot
+    bot commands export [--format json]          Print every command as Discord API JSON
+    bot commands diff <file>                     Compare every command with an earlier export
+    bot commands push (--guild <id> | --global)  Register every command
+    bot commands clear (--guild <id> | --global) Unregister every command";
+
+/// `Cli` is a subcommand of the binary, which works with the registry without connecting to the
+/// gateway.
+#[derive(Debug)]
+pub enum Cli {
+    /// `commands export` - prints the manifest of every command.
+    Export,
+
+    /// `commands diff <file>` - compares the manifest with one exported earlier.
+    Diff(PathBuf),
+
+    /// `commands push` - syncs the commands of a scope with the registry.
+    Push(Scope),
+
+    /// `commands clear` - deletes the commands of a scope.
+    Clear(Scope),
+}
+
+impl Cli {
+    /// Parses the arguments the binary was run with, without the binary itself. There's nothing
+    /// to parse when the bot is just being run.
+    ///
+    /// # Errors
+    ///
+    /// Returns what's wrong with the arguments.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let args: Vec<String> = args.into_iter().collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let cli = match args.as_slice() {
+            [] => return Ok(None),
+            ["commands", "export"] | ["commands", "export", "--format", "json"] => Self::Export,
+            ["commands", "export", "--format", format] => {
+                return Err(format!("unknown format `{format}`, only `json` is supported"));
+            }
+            ["commands", "diff", file] => Self::Diff(PathBuf::from(file)),
+            ["commands", "push", scope @ ..] => Self::Push(parse_scope(scope)?),
+            ["commands", "clear", scope @ ..] => Self::Clear(parse_scope(scope)?),
+            _ => return Err(format!("unknown arguments `{}`", args.join(" "))),
+        };
+
+        Ok(Some(cli))
+    }
+
+    /// Runs the subcommand, only reading the token if it talks to Discord.
+    ///
+    /// # Errors
+    ///
+    /// Returns why it failed, or that the manifest differs when diffing.
+    pub async fn run(self, token: fn() -> String) -> Result<(), String> {
+        match self {
+            Self::Export => {
+                let manifest = json
+                    ::to_string_pretty(&manifest())
+                    .map_err(|why| format!("can't serialize the commands: {why}"))?;
+                println!("{manifest}");
+                Ok(())
+            }
+            Self::Diff(file) => diff(&file),
+            Self::Push(scope) => {
+                let commands: Vec<_> = registry::commands().collect();
+                push(&Http::new(&token()), scope, &commands).await
+            }
+            Self::Clear(scope) => push(&Http::new(&token()), scope, &[]).await,
+        }
+    }
+}
+
+fn parse_scope(args: &[&str]) -> Result<Scope, String> {
+    match args {
+        ["--global"] => Ok(Scope::Global),
+        ["--guild", guild] => {
+            guild
+                .parse()
+                .map(Scope::Guild)
+                .map_err(|_| format!("`{guild}` isn't a guild id"))
+        }
+        _ => Err("expected `--guild <id>` or `--global`".to_owned()),
+    }
+}
+
+/// Every registered command as Discord receives it, ordered by kind and name so exports diff
+/// cleanly.
+fn manifest() -> Vec<Value> {
+    let mut commands: Vec<&SlashCommand> = registry::commands().collect();
+    commands.sort_unstable_by_key(|command| (u8::from(command.kind), command.name));
+
+    commands
+        .into_iter()
+        .map(|command| json::to_value((command.register)()).expect("a `CreateCommand` always serializes"))
+        .collect()
+}
+
+/// Keys the commands of a manifest by kind and name, pruned of the fields left at their
+/// defaults.
+fn keyed(manifest: Vec<Value>) -> Result<BTreeMap<(u64, String), Value>, String> {
+    manifest
+        .into_iter()
+        .map(|mut command| {
+            let name = command
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or("a command in the manifest has no name")?
+                .to_owned();
+            let kind = command
+                .get("type")
+                .and_then(Value::as_u64)
+                .unwrap_or_else(|| u8::from(CommandType::ChatInput).into());
+
+            sync::prune(&mut command);
+            Ok(((kind, name), command))
+        })
+        .collect()
+}
+
+/// Prints how the manifest differs from the one exported to `file`.
+fn diff(file: &Path) -> Result<(), String> {
+    let exported = fs
+        ::read_to_string(file)
+        .map_err(|why| format!("can't read `{}`: {why}", file.display()))?;
+    let exported: Vec<Value> = json
+        ::from_str(exported)
+        .map_err(|why| format!("`{}` isn't an exported manifest: {why}", file.display()))?;
+
+    let mut exported = keyed(exported)?;
+    let mut differences = 0;
+    for (key, command) in keyed(manifest())? {
+        match exported.remove(&key) {
+            None => println!("+ {}", key.1),
+            Some(before) if before != command => println!("~ {}", key.1),
+            Some(_) => continue,
+        }
+        differences += 1;
+    }
+    for (_, name) in exported.into_keys() {
+        println!("- {name}");
+        differences += 1;
+    }
+
+    if differences == 0 {
+        println!("The commands match `{}`", file.display());
+        Ok(())
+    } else {
+        Err(format!("{differences} command(s) differ from `{}`", file.display()))
+    }
+}
+
+/// Syncs the commands of `scope` with `commands`.
+async fn push(http: &Http, scope: Scope, commands: &[&'static SlashCommand]) -> Result<(), String> {
+    let summary = sync::sync(http, scope, commands).await.map_err(|why| why.to_string())?;
+    println!("Synced {scope}: {summary}");
+    Ok(())
+}
@@ -86,8 +86,8 @@ impl Values {
     ///
     /// A new instance of `Values`.
     #[inline]
-    pub fn new(name: Ident, kind: ValueKind, literals: Vec<Lit>, span: Span) -> Self {
-        Values {
+    pub const fn new(name: Ident, kind: ValueKind, literals: Vec<Lit>, span: Span) -> Self {
+        Self {
             name,
             literals,
             kind,
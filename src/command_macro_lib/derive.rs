@@ -0,0 +1,294 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::Error,
+    spanned::Spanned,
+    Attribute,
+    Data,
+    DeriveInput,
+    Fields,
+    GenericArgument,
+    GenericParam,
+    Lifetime,
+    LifetimeParam,
+    Lit,
+    PathArguments,
+    Result,
+    Type,
+};
+
+use crate::{
+    localization::Localizations,
+    structures::is_rustfmt_or_clippy_attr,
+    util::parse_values,
+    validate,
+};
+
+/// Expands `#[derive(SlashOptions)]` for a struct with named fields.
+///
+/// Every field becomes one option, named after the field unless `#[name("..")]` says otherwise
+/// and described by its `#[description("..")]`. Fields of type `Option<T>` are registered as
+/// optional, everything else is required. Fields marked `#[autocomplete]` are registered with
+/// autocomplete turned on, to be served by an `#[autocomplete(option = "..")]` hook.
+pub fn slash_options(input: &DeriveInput) -> Result<TokenStream2> {
+    let ident = &input.ident;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(input.span(), "`SlashOptions` can only be derived for structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(data.fields.span(), "`SlashOptions` needs named fields"));
+    };
+
+    if fields.named.len() > validate::MAX_OPTIONS {
+        return Err(
+            Error::new(
+                fields.span(),
+                format_args!("at most {} options are allowed", validate::MAX_OPTIONS)
+            )
+        );
+    }
+
+    let mut creates = Vec::with_capacity(fields.named.len());
+    let mut parses = Vec::with_capacity(fields.named.len());
+    let mut optional: Option<String> = None;
+
+    for field in &fields.named {
+        let Some(field_name) = &field.ident else {
+            continue;
+        };
+
+        let FieldAttrs { name, description, autocomplete, localizations } = FieldAttrs::parse(
+            &field.attrs
+        )?;
+        let name = name.unwrap_or_else(|| field_name.to_string());
+        let Some(description) = description else {
+            return Err(
+                Error::new(field.span(), "missing `#[description(\"..\")]` for this option")
+            );
+        };
+
+        validate::name(&name, field.span())?;
+        validate::description(&description, field.span())?;
+        validate::localizations(&localizations, field.span())?;
+
+        let (kind, required, extract) = option_inner(&field.ty).map_or_else(
+            || (&field.ty, true, quote!(required)),
+            |inner| (inner, false, quote!(optional))
+        );
+
+        validate::option_order(&name, required, &mut optional, field.span())?;
+
+        creates.push(
+            quote! {
+            <#kind as crate::interactions::options::FromResolvedValue>::decorate(
+                serenity::builder::CreateCommandOption::new(
+                    <#kind as crate::interactions::options::FromResolvedValue>::KIND,
+                    #name,
+                    #description
+                ).required(#required).set_autocomplete(#autocomplete) #localizations
+            )
+        }
+        );
+        parses.push(
+            quote! {
+            #field_name: crate::interactions::options::#extract(options, #name)?,
+        }
+        );
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Options borrowing from the interaction (`&'a str`, `&'a PartialChannel`) share the
+    // struct's first lifetime, structs without one get a fresh lifetime for the slice.
+    let lifetime = input.generics.lifetimes().next().map_or_else(
+        || Lifetime::new("'options", proc_macro2::Span::call_site()),
+        |param| param.lifetime.clone()
+    );
+    let mut try_from_generics = input.generics.clone();
+    if input.generics.lifetimes().next().is_none() {
+        try_from_generics.params.insert(
+            0,
+            GenericParam::Lifetime(LifetimeParam::new(lifetime.clone()))
+        );
+    }
+    let (try_from_impl_generics, _, _) = try_from_generics.split_for_impl();
+
+    Ok(
+        quote! {
+        impl #impl_generics crate::interactions::options::SlashOptions for #ident #ty_generics #where_clause {
+            fn create_options() -> Vec<serenity::builder::CreateCommandOption> {
+                vec![#(#creates),*]
+            }
+        }
+
+        impl #try_from_impl_generics TryFrom<&#lifetime [serenity::all::ResolvedOption<#lifetime>]>
+            for #ident #ty_generics #where_clause
+        {
+            type Error = crate::interactions::options::OptionError;
+
+            fn try_from(
+                options: &#lifetime [serenity::all::ResolvedOption<#lifetime>]
+            ) -> Result<Self, Self::Error> {
+                Ok(Self {
+                    #(#parses)*
+                })
+            }
+        }
+    }
+    )
+}
+
+/// Expands `#[derive(SlashChoice)]` for an enum of unit variants.
+///
+/// The enum is registered as a string option with one choice per variant. The choice value is
+/// the variant name in snake case unless `#[name("..")]` says otherwise, and its display name
+/// is the `#[description("..")]` if there is one, translated by any `#[name_localized(..)]`.
+pub fn slash_choice(input: &DeriveInput) -> Result<TokenStream2> {
+    let ident = &input.ident;
+
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(input.span(), "`SlashChoice` can only be derived for enums"));
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(input.generics.span(), "`SlashChoice` enums can't be generic"));
+    }
+
+    let mut choices = Vec::with_capacity(data.variants.len());
+    let mut matches = Vec::with_capacity(data.variants.len());
+
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new(variant.span(), "`SlashChoice` variants can't hold data"));
+        }
+
+        let variant_ident = &variant.ident;
+        let FieldAttrs { name, description, localizations, .. } = FieldAttrs::parse(&variant.attrs)?;
+        let value = name.unwrap_or_else(|| to_snake_case(&variant_ident.to_string()));
+        let display = description.unwrap_or_else(|| value.clone());
+
+        if localizations.names.is_empty() {
+            choices.push(quote!(.add_string_choice(#display, #value)));
+        } else {
+            let locales = localizations.names.iter().map(|(locale, name)| quote!((#locale, #name)));
+            choices.push(quote!(.add_string_choice_localized(#display, #value, [#(#locales),*])));
+        }
+        matches.push(quote!(#value => Some(Self::#variant_ident),));
+    }
+
+    Ok(
+        quote! {
+        impl<'a> crate::interactions::options::FromResolvedValue<'a> for #ident {
+            const KIND: serenity::all::CommandOptionType = serenity::all::CommandOptionType::String;
+            const EXPECTED: &'static str = "one of the listed choices";
+
+            fn from_resolved(value: &serenity::all::ResolvedValue<'a>) -> Option<Self> {
+                let value = <&str as crate::interactions::options::FromResolvedValue>::from_resolved(value)?;
+
+                match value {
+                    #(#matches)*
+                    _ => None,
+                }
+            }
+
+            fn decorate(
+                option: serenity::builder::CreateCommandOption
+            ) -> serenity::builder::CreateCommandOption {
+                option #(#choices)*
+            }
+        }
+    }
+    )
+}
+
+/// The `#[name("..")]`, `#[description("..")]`, `#[autocomplete]`, `#[name_localized(..)]` and
+/// `#[description_localized(..)]` attributes of a field or variant.
+struct FieldAttrs {
+    name: Option<String>,
+    description: Option<String>,
+    autocomplete: bool,
+    localizations: Localizations,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut name = None;
+        let mut description = None;
+        let mut autocomplete = false;
+        let mut localizations = Localizations::default();
+
+        for attr in attrs {
+            if attr.path().is_ident("autocomplete") {
+                autocomplete = true;
+                continue;
+            }
+
+            if attr.path().is_ident("name_localized") || attr.path().is_ident("description_localized") {
+                let values = parse_values(attr)?;
+                localizations.push(&values, &values.literals)?;
+                continue;
+            }
+
+            if
+                is_rustfmt_or_clippy_attr(attr.path()) ||
+                !(attr.path().is_ident("name") || attr.path().is_ident("description"))
+            {
+                continue;
+            }
+
+            let values = parse_values(attr)?;
+            let value = match values.literals.first() {
+                Some(Lit::Str(lit_str)) => lit_str.value(),
+                _ => {
+                    return Err(Error::new(values.span, "expected a string literal"));
+                }
+            };
+
+            match values.name.to_string().as_str() {
+                "name" => name = Some(value),
+                "description" => description = Some(value),
+                _ => {}
+            }
+        }
+
+        Ok(Self { name, description, autocomplete, localizations })
+    }
+}
+
+/// Returns `T` if `ty` is written as `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+
+    match arguments.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+
+    snake
+}
@@ -0,0 +1,756 @@
+use proc_macro2::{ Span, TokenStream as TokenStream2 };
+use quote::{ format_ident, quote, ToTokens };
+use syn::{ spanned::Spanned, Error, Ident, ItemFn, LitStr, Result };
+
+use crate::localization::Catalog;
+use crate::structures::{
+    no_generics_in,
+    CommandAttrs,
+    CommandCooldown,
+    CommandFun,
+    CommandKind,
+    HandlerKind,
+    MenuKind,
+    RunFn,
+};
+use crate::util::{ ArgSource, Argument };
+use crate::validate;
+
+/// Expands a `#[slash_command]` module, or a `#[subcommand]`/`#[subcommand_group]` module
+/// nested inside one, into the module the rest of the bot uses.
+///
+/// Every module gets a `register` function and an `invoke` function taking the options at its
+/// own level of the command tree. Modules holding subcommands route `invoke` to the matching
+/// child, and the top-level module submits itself into the command registry.
+pub fn command(func: &CommandFun) -> Result<TokenStream2> {
+    module(func, None)
+}
+
+/// Expands a single module of the command tree. `catalog` is the module's part of its
+/// command's `#[localizations("..")]` catalog, if it has one.
+fn module(func: &CommandFun, catalog: Option<&Catalog>) -> Result<TokenStream2> {
+    let name = &func.name;
+    let visibility = &func.visibility;
+    let items = &func.items;
+
+    let mut command = CommandAttrs::parse(&func.attrs)?;
+    if func.kind != CommandKind::Command {
+        if let Some(attr) = command.top_level_attr() {
+            return Err(
+                syn::Error::new(
+                    func.name.span(),
+                    format_args!("`#[{attr}]` goes on the top-level `#[slash_command]` module")
+                )
+            );
+        }
+    }
+    for hook in &func.autocompletes {
+        if let Some(option) = command.options.iter_mut().find(|option| option.name == hook.option) {
+            option.autocomplete = true;
+        }
+    }
+
+    let (loaded, include_catalog) = match &command.catalog {
+        Some(path) => {
+            let (loaded, include_catalog) = Catalog::load(path, &name.to_string())?;
+            (Some(loaded), Some(include_catalog))
+        }
+        None => (None, None),
+    };
+    let catalog = loaded.as_ref().or(catalog);
+    if let Some(catalog) = catalog {
+        catalog.localize(&mut command.localizations)?;
+        for option in &mut command.options {
+            catalog.localize_option(&option.name, &mut option.localizations)?;
+        }
+    }
+    validate::command(func, &command)?;
+
+    let Parts { register, run, invoke, autocomplete } = match &func.run {
+        Some(run) => leaf(func, run, &command)?,
+        None => branch(func, &command),
+    };
+    let hooks = func.autocompletes.iter().map(|hook| &hook.func);
+
+    let children = func.subcommands
+        .iter()
+        .map(|sub| {
+            let sub_catalog = catalog.map(|catalog| catalog.child(&sub.name.to_string())).transpose()?;
+            module(sub, sub_catalog.as_ref())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let registry_entry = (func.kind == CommandKind::Command).then(|| {
+        registry_entry(&command, &name.to_string(), &Ident::new("ChatInput", Span::call_site()))
+    });
+
+    Ok(
+        quote! {
+        #visibility mod #name {
+            #(#items)*
+
+            #register
+
+            #run
+
+            #(#hooks)*
+
+            #invoke
+
+            #autocomplete
+
+            #registry_entry
+
+            #include_catalog
+
+            #(#children)*
+        }
+    }
+    )
+}
+
+/// Expands a `#[user_command]` or `#[message_command]` module. These are a single `run`
+/// function, called with the user or message they were used on, so they share the top-level
+/// slash command's `invoke` and registry entry but none of its options.
+pub fn context_menu(func: &CommandFun, kind: MenuKind, menu: &LitStr) -> Result<TokenStream2> {
+    let CommandFun { name, visibility, items, .. } = func;
+
+    let command = CommandAttrs::parse(&func.attrs)?;
+    validate::context_menu(func, kind, menu, &command)?;
+    let Some(run) = &func.run else {
+        return Err(Error::new(name.span(), "expected a `run` function"));
+    };
+
+    let command_type = kind.command_type();
+    let localizations = &command.localizations;
+    let restrictions = restrictions(&command);
+    let run_fn = &run.func;
+    let invoke = invoke_fn(func, run, &command, Some(kind))?;
+    let autocomplete = autocomplete_fn(func);
+    let registry_entry = registry_entry(&command, &menu.value(), &command_type);
+
+    Ok(
+        quote! {
+        #visibility mod #name {
+            #(#items)*
+
+            #visibility fn register() -> serenity::builder::CreateCommand {
+                serenity::builder::CreateCommand::new(#menu)
+                    .kind(serenity::all::CommandType::#command_type)
+                    #localizations
+                    #restrictions
+            }
+
+            #run_fn
+
+            #invoke
+
+            #autocomplete
+
+            #registry_entry
+        }
+    }
+    )
+}
+
+/// Generates the top-level module's entry points and its entry in the command registry, under
+/// `name` as a command of the `CommandType` variant `kind`.
+fn registry_entry(command: &CommandAttrs, name: &str, kind: &Ident) -> TokenStream2 {
+    let defer = command.defer
+        .clone()
+        .unwrap_or_else(|| quote!(OnBudget { ephemeral: true }));
+    let CommandAttrs { permissions, nsfw, owners_only, cooldowns, .. } = command;
+    // `#[dm_permission(false)]` is enforced when run like `#[guild_only]`, not only registered
+    let guild_only = command.guild_only || command.dm_permission == Some(false);
+    let category = command.category
+        .as_ref()
+        .map_or_else(|| quote!(None), |category| quote!(Some(#category)));
+
+    quote! {
+        fn dispatch<'a>(
+            ctx: &'a serenity::prelude::Context,
+            command: &'a serenity::all::CommandInteraction
+        ) -> serenity::futures::future::BoxFuture<'a, crate::interactions::error::CommandResult> {
+            invoke(ctx, command, command.data.options())
+        }
+
+        fn dispatch_autocomplete<'a>(
+            ctx: &'a serenity::prelude::Context,
+            command: &'a serenity::all::CommandInteraction
+        ) -> serenity::futures::future::BoxFuture<'a, Vec<serenity::builder::AutocompleteChoice>> {
+            autocomplete(ctx, command, command.data.options())
+        }
+
+        inventory::submit! {
+            crate::interactions::registry::SlashCommand {
+                name: #name,
+                kind: serenity::all::CommandType::#kind,
+                register,
+                run: dispatch,
+                autocomplete: dispatch_autocomplete,
+                defer: crate::interactions::registry::Defer::#defer,
+                checks: crate::interactions::checks::Checks {
+                    permissions: serenity::all::Permissions::from_bits_truncate(
+                        0 #(| serenity::all::Permissions::#permissions.bits())*
+                    ),
+                    guild_only: #guild_only,
+                    nsfw: #nsfw,
+                    owners_only: #owners_only,
+                },
+                cooldowns: &[#(#cooldowns),*],
+                category: #category,
+                module: module_path!(),
+            }
+        }
+    }
+}
+
+/// Expands `#[cooldown(..)]` on a prefix `#[command]` function, which is left as it is next to
+/// the cooldown it submits under the function's name.
+pub fn prefix_cooldown(cooldown: &CommandCooldown, func: &ItemFn) -> TokenStream2 {
+    let command = func.sig.ident.to_string();
+
+    quote! {
+        #func
+
+        inventory::submit! {
+            crate::cooldown::PrefixCooldown {
+                command: #command,
+                cooldown: #cooldown,
+            }
+        }
+    }
+}
+
+/// Expands a `#[component("..")]` or `#[modal("..")]` function, which is left as it is next to
+/// the handler it submits under `prefix`.
+///
+/// The function is called as `handler(ctx: &Context, interaction: &<Component|Modal>Interaction,
+/// id: &CustomId)` and returns a `ComponentResponse`, anything that converts into one, or a
+/// `Result` of it.
+pub fn handler(kind: HandlerKind, prefix: &LitStr, func: &ItemFn) -> Result<TokenStream2> {
+    validate::prefix(&prefix.value(), prefix.span())?;
+    no_generics_in(func, kind.attribute())?;
+
+    let name = &func.sig.ident;
+    let dispatch = format_ident!("dispatch_{}", name);
+    let (entry, interaction) = kind.types();
+    let await_token = func.sig.asyncness.map(|_| quote!(.await));
+
+    Ok(
+        quote! {
+        #func
+
+        fn #dispatch<'a>(
+            ctx: &'a serenity::prelude::Context,
+            interaction: &'a serenity::all::#interaction,
+            id: crate::interactions::components::CustomId
+        ) -> serenity::futures::future::BoxFuture<'a, crate::interactions::components::ComponentResult> {
+            Box::pin(async move {
+                crate::interactions::components::IntoComponentResult::into_component_result(
+                    #name(ctx, interaction, &id)#await_token
+                )
+            })
+        }
+
+        inventory::submit! {
+            crate::interactions::components::#entry {
+                prefix: #prefix,
+                run: #dispatch,
+            }
+        }
+    }
+    )
+}
+
+/// The generated functions of a single module.
+struct Parts {
+    register: TokenStream2,
+    run: TokenStream2,
+    invoke: TokenStream2,
+    autocomplete: TokenStream2,
+}
+
+/// Generates the functions of a module with a `run` function of its own.
+fn leaf(func: &CommandFun, run: &RunFn, command: &CommandAttrs) -> Result<Parts> {
+    let struct_options: Vec<_> = run.args
+        .iter()
+        .filter(|arg| is_options_struct(arg))
+        .map(|arg| {
+            let kind = &arg.kind;
+            quote!(<#kind as crate::interactions::options::SlashOptions>::create_options())
+        })
+        .collect();
+    let options = &command.options;
+
+    let register = register_fn(func, command, |builder| {
+        if func.kind == CommandKind::Command {
+            quote! {
+                #builder
+                    #(.set_options(#struct_options))*
+                    #(.add_option(#options))*
+            }
+        } else if struct_options.is_empty() {
+            quote!(#builder #(.add_sub_option(#options))*)
+        } else {
+            quote! {
+                [#(#struct_options),*]
+                    .into_iter()
+                    .flatten()
+                    .fold(#builder, serenity::builder::CreateCommandOption::add_sub_option)
+                    #(.add_sub_option(#options))*
+            }
+        }
+    });
+
+    let invoke = invoke_fn(func, run, command, None)?;
+    let autocomplete = autocomplete_fn(func);
+
+    Ok(Parts { register, run: run.func.to_token_stream(), invoke, autocomplete })
+}
+
+/// Generates `register` and a routing `invoke` and `autocomplete` for a module holding
+/// subcommands.
+fn branch(func: &CommandFun, command: &CommandAttrs) -> Parts {
+    let children: Vec<_> = func.subcommands
+        .iter()
+        .map(|sub| &sub.name)
+        .collect();
+    let child_names: Vec<_> = children.iter().map(ToString::to_string).collect();
+
+    let register = register_fn(func, command, |builder| {
+        if func.kind == CommandKind::Command {
+            quote!(#builder #(.add_option(#children::register()))*)
+        } else {
+            quote!(#builder #(.add_sub_option(#children::register()))*)
+        }
+    });
+
+    let visibility = invoke_visibility(func);
+    let invoke =
+        quote! {
+        #visibility fn invoke<'a>(
+            ctx: &'a serenity::prelude::Context,
+            command: &'a serenity::all::CommandInteraction,
+            options: Vec<serenity::all::ResolvedOption<'a>>
+        ) -> serenity::futures::future::BoxFuture<'a, crate::interactions::error::CommandResult> {
+            Box::pin(async move {
+                for option in options {
+                    if let
+                        serenity::all::ResolvedValue::SubCommand(options) |
+                        serenity::all::ResolvedValue::SubCommandGroup(options) = option.value
+                    {
+                        match option.name {
+                            #(#child_names => return #children::invoke(ctx, command, options).await,)*
+                            _ => {}
+                        }
+                    }
+                }
+
+                Err(crate::interactions::error::CommandError::internal("unknown subcommand"))
+            })
+        }
+    };
+
+    let autocomplete =
+        quote! {
+        #visibility fn autocomplete<'a>(
+            ctx: &'a serenity::prelude::Context,
+            command: &'a serenity::all::CommandInteraction,
+            options: Vec<serenity::all::ResolvedOption<'a>>
+        ) -> serenity::futures::future::BoxFuture<'a, Vec<serenity::builder::AutocompleteChoice>> {
+            Box::pin(async move {
+                for option in options {
+                    if let
+                        serenity::all::ResolvedValue::SubCommand(options) |
+                        serenity::all::ResolvedValue::SubCommandGroup(options) = option.value
+                    {
+                        match option.name {
+                            #(#child_names => return #children::autocomplete(ctx, command, options).await,)*
+                            _ => {}
+                        }
+                    }
+                }
+
+                Vec::new()
+            })
+        }
+    };
+
+    Parts { register, run: quote!(), invoke, autocomplete }
+}
+
+/// Generates `register`, building a `CreateCommand` at the top level and a
+/// `CreateCommandOption` for nested modules. `finish` adds the options or children.
+fn register_fn(
+    func: &CommandFun,
+    command: &CommandAttrs,
+    finish: impl FnOnce(TokenStream2) -> TokenStream2
+) -> TokenStream2 {
+    let name = func.name.to_string();
+    let description = command.description.clone().unwrap_or_default();
+    let localizations = &command.localizations;
+
+    match func.kind {
+        CommandKind::Command => {
+            let visibility = &func.visibility;
+            let restrictions = restrictions(command);
+
+            let builder = finish(
+                quote! {
+                serenity::builder::CreateCommand::new(#name)
+                    .description(#description)
+                    #localizations
+                    #restrictions
+            }
+            );
+
+            quote! {
+                #visibility fn register() -> serenity::builder::CreateCommand {
+                    #builder
+                }
+            }
+        }
+        CommandKind::SubCommand | CommandKind::SubCommandGroup => {
+            let kind = Ident::new(
+                if func.kind == CommandKind::SubCommand { "SubCommand" } else { "SubCommandGroup" },
+                Span::call_site()
+            );
+            let builder = finish(
+                quote! {
+                serenity::builder::CreateCommandOption::new(
+                    serenity::all::CommandOptionType::#kind,
+                    #name,
+                    #description
+                ) #localizations
+            }
+            );
+
+            quote! {
+                pub(super) fn register() -> serenity::builder::CreateCommandOption {
+                    #builder
+                }
+            }
+        }
+    }
+}
+
+/// Generates the `CreateCommand` calls for who can see a top-level command and where, from
+/// `#[default_member_permissions(..)]`, `#[guild_only]`, `#[dm_permission]` and `#[nsfw]`.
+fn restrictions(command: &CommandAttrs) -> TokenStream2 {
+    let permissions = (!command.permissions.is_empty()).then(|| {
+        let permissions = &command.permissions;
+        quote!(.default_member_permissions(#(serenity::all::Permissions::#permissions)|*))
+    });
+    let dm_permission = command.dm_permission
+        .or_else(|| command.guild_only.then_some(false))
+        .map(|enabled| quote!(.dm_permission(#enabled)));
+    let nsfw = command.nsfw.then(|| quote!(.nsfw(true)));
+
+    quote!(#permissions #dm_permission #nsfw)
+}
+
+/// Binds an option argument of `run` from the interaction's options, failing the command with
+/// the `OptionError` as a user error if it can't be.
+fn extract_option(command: &CommandAttrs, arg: &Argument) -> TokenStream2 {
+    let Argument { name: arg_name, kind, .. } = arg;
+
+    let extract = if is_options_struct(arg) {
+        quote!(<#kind>::try_from(options.as_slice()))
+    } else {
+        let option_name = arg_name.to_string();
+        let required = command.option(&option_name).is_some_and(|option| option.required);
+        let extract = if required { quote!(required) } else { quote!(optional) };
+
+        quote!(crate::interactions::options::#extract(&options, #option_name))
+    };
+
+    let local = option_local(arg);
+    quote! {
+        let #local: #kind = match #extract {
+            Ok(value) => value,
+            Err(why) => return Err(crate::interactions::error::CommandError::from(why)),
+        };
+    }
+}
+
+/// The local an extracted option is bound to in `invoke`, prefixed so options called e.g.
+/// `command` or `options` don't shadow `invoke`'s own arguments.
+fn option_local(arg: &Argument) -> Ident {
+    format_ident!("option_{}", arg.name)
+}
+
+/// Generates the `invoke` function for a module with a `run` function, which pulls every
+/// `run` argument out of the context, interaction and options and converts what `run`
+/// returns into a `CommandResult`. `menu` is set for context-menu commands, which are also
+/// passed what they were used on.
+fn invoke_fn(
+    func: &CommandFun,
+    run: &RunFn,
+    command: &CommandAttrs,
+    menu: Option<MenuKind>
+) -> Result<TokenStream2> {
+    // Arguments named after a declared option, or marked `#[options]`, are filled in from the
+    // interaction's options, everything else is passed through from the interaction as-is.
+    let is_option = |arg: &Argument| {
+        is_options_struct(arg) || command.option(&arg.name.to_string()).is_some()
+    };
+
+    let extractions: Vec<_> = run.args
+        .iter()
+        .filter(|arg| is_option(arg))
+        .map(|arg| extract_option(command, arg))
+        .collect();
+    let sources = run.args
+        .iter()
+        .filter(|arg| !is_option(arg))
+        .map(|arg| {
+            let source = arg.source()?;
+            match source.menu() {
+                Some(target) if Some(target) != menu => {
+                    Err(
+                        Error::new(
+                            arg.kind.span(),
+                            format_args!("only a `#[{}]` is passed what it's used on", target.attribute())
+                        )
+                    )
+                }
+                _ => Ok(source),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let guild_check = sources.contains(&ArgSource::GuildId).then(|| {
+        quote! {
+            let Some(guild_id) = command.guild_id else {
+                return Err(
+                    crate::interactions::error::CommandError::user("This command must be used in a guild.")
+                );
+            };
+        }
+    });
+
+    let target_check = menu
+        .filter(|_| sources.iter().any(|source| source.menu().is_some()))
+        .map(|menu| {
+            let target = match menu {
+                MenuKind::User => quote!(User(target_user, target_member)),
+                MenuKind::Message => quote!(Message(target_message)),
+            };
+
+            quote! {
+                let Some(serenity::all::ResolvedTarget::#target) = command.data.target() else {
+                    return Err(
+                        crate::interactions::error::CommandError::internal("the command's target isn't resolved")
+                    );
+                };
+            }
+        });
+
+    let mut sources = sources.into_iter();
+    let call_args = run.args.iter().map(|arg| {
+        if is_option(arg) {
+            let local = option_local(arg);
+            return quote!(#local);
+        }
+
+        match sources.next() {
+            Some(ArgSource::Context) => quote!(ctx),
+            Some(ArgSource::GuildId) => quote!(guild_id),
+            Some(ArgSource::Interaction) => quote!(command),
+            Some(ArgSource::TargetUser) => quote!(target_user),
+            Some(ArgSource::TargetMember) => quote!(target_member),
+            Some(ArgSource::TargetMessage) => quote!(target_message),
+            Some(ArgSource::Options) | None => quote!(&options),
+        }
+    });
+    let await_token = if run.is_async() { quote!(.await) } else { quote!() };
+    let run_name = run.name();
+    let visibility = invoke_visibility(func);
+
+    Ok(
+        quote! {
+        #[allow(unused_variables)]
+        #visibility fn invoke<'a>(
+            ctx: &'a serenity::prelude::Context,
+            command: &'a serenity::all::CommandInteraction,
+            options: Vec<serenity::all::ResolvedOption<'a>>
+        ) -> serenity::futures::future::BoxFuture<'a, crate::interactions::error::CommandResult> {
+            Box::pin(async move {
+                #guild_check
+
+                #target_check
+
+                #(#extractions)*
+
+                crate::interactions::error::IntoCommandResult::into_command_result(
+                    #run_name(#(#call_args),*)#await_token
+                )
+            })
+        }
+    }
+    )
+}
+
+/// Generates the `autocomplete` function for a module with a `run` function, which hands the
+/// focused option's value to its `#[autocomplete(option = "..")]` hook.
+fn autocomplete_fn(func: &CommandFun) -> TokenStream2 {
+    let visibility = invoke_visibility(func);
+    let routes = func.autocompletes.iter().map(|hook| {
+        let option = &hook.option;
+        let hook_name = &hook.func.sig.ident;
+        let await_token = hook.func.sig.asyncness.map(|_| quote!(.await));
+
+        quote!(#option => #hook_name(ctx, command, focused.value)#await_token,)
+    });
+
+    quote! {
+        #[allow(unused_variables)]
+        #visibility fn autocomplete<'a>(
+            ctx: &'a serenity::prelude::Context,
+            command: &'a serenity::all::CommandInteraction,
+            options: Vec<serenity::all::ResolvedOption<'a>>
+        ) -> serenity::futures::future::BoxFuture<'a, Vec<serenity::builder::AutocompleteChoice>> {
+            Box::pin(async move {
+                let Some(focused) = command.data.autocomplete() else {
+                    return Vec::new();
+                };
+
+                match focused.name {
+                    #(#routes)*
+                    _ => Vec::new(),
+                }
+            })
+        }
+    }
+}
+
+/// Nested modules' `invoke` is called by their parent, the top-level one only by `dispatch`.
+fn invoke_visibility(func: &CommandFun) -> TokenStream2 {
+    if func.kind == CommandKind::Command { quote!() } else { quote!(pub(super)) }
+}
+
+pub fn is_options_struct(arg: &Argument) -> bool {
+    arg.attrs.iter().any(|attr| attr.path().is_ident("options"))
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::TokenStream as TokenStream2;
+    use quote::quote;
+
+    use crate::structures::{ CommandFun, MenuKind };
+
+    /// Expands a `#[slash_command]` module and pretty prints it for a snapshot.
+    fn expand(tokens: TokenStream2) -> String {
+        let func: CommandFun = syn::parse2(tokens).expect("module should parse");
+
+        unparse(super::command(&func).expect("module should expand"))
+    }
+
+    /// Expands a context-menu command module called `menu` and pretty prints it for a snapshot.
+    fn expand_menu(kind: MenuKind, menu: &str, tokens: TokenStream2) -> String {
+        let func: CommandFun = syn::parse2(tokens).expect("module should parse");
+        let menu = syn::LitStr::new(menu, proc_macro2::Span::call_site());
+
+        unparse(super::context_menu(&func, kind, &menu).expect("module should expand"))
+    }
+
+    fn unparse(expanded: TokenStream2) -> String {
+        let file = syn::parse2(expanded).expect("expansion should be valid items");
+
+        prettyplease::unparse(&file)
+    }
+
+    #[test]
+    fn sync_command() {
+        insta::assert_snapshot!(expand(quote! {
+            #[description("It Pings")]
+            #[option(string, "message", "What to reply with")]
+            mod ping {
+                pub fn run(message: Option<String>) -> String {
+                    message.unwrap_or_else(|| "Pong!".to_owned())
+                }
+            }
+        }));
+    }
+
+    #[test]
+    fn async_command() {
+        insta::assert_snapshot!(expand(quote! {
+            #[description("Get user ids")]
+            #[guild_only]
+            #[defer]
+            #[cooldown(user, 30)]
+            #[default_member_permissions(MANAGE_CHANNELS, KICK_MEMBERS)]
+            #[category("Members")]
+            mod id {
+                use crate::interactions::error::CommandError;
+
+                const LIMIT: usize = 2000;
+
+                pub async fn run(
+                    ctx: &Context,
+                    guild_id: GuildId,
+                    #[options] args: IdOptions
+                ) -> Result<String, CommandError> {
+                    Ok(String::new())
+                }
+
+                #[autocomplete(option = "role")]
+                pub async fn suggest_role(
+                    ctx: &Context,
+                    command: &CommandInteraction,
+                    value: &str
+                ) -> Vec<AutocompleteChoice> {
+                    Vec::new()
+                }
+            }
+        }));
+    }
+
+    #[test]
+    fn subcommands() {
+        insta::assert_snapshot!(expand(quote! {
+            #[description("Creates a meeting")]
+            #[description_localized("de", "Erstellt ein Treffen")]
+            mod create_meeting {
+                #[subcommand]
+                #[description("Creates a meeting with a single user")]
+                #[option(user, "user", "The user to meet with", required)]
+                mod user {
+                    #[run]
+                    pub fn create(user: UserId) -> CommandResponse {
+                        CommandResponse::new()
+                    }
+                }
+
+                #[subcommand_group]
+                #[description("Meetings with roles")]
+                mod roles {
+                    #[subcommand]
+                    #[description("Creates a meeting with everyone in a role")]
+                    #[option(role, "role", "The role to meet with", required)]
+                    mod role {
+                        pub fn run(role: RoleId) -> CommandResponse {
+                            CommandResponse::new()
+                        }
+                    }
+                }
+            }
+        }));
+    }
+
+    #[test]
+    fn user_command() {
+        insta::assert_snapshot!(expand_menu(MenuKind::User, "Get IDs", quote! {
+            #[guild_only]
+            #[name_localized("de", "IDs anzeigen")]
+            mod user_ids {
+                pub fn run(user: &User, member: Option<&PartialMember>) -> String {
+                    user.id.to_string()
+                }
+            }
+        }));
+    }
+}
@@ -1,76 +1,116 @@
-#![feature(allocator_api)]
-
 pub(crate) mod structures;
 pub(crate) mod attributes;
+pub(crate) mod derive;
+pub(crate) mod expand;
+pub(crate) mod localization;
+pub(crate) mod validate;
 #[macro_use]
 pub(crate) mod util;
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Lit};
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{parse_macro_input, parse_quote, Attribute, DeriveInput, ItemFn, LitStr};
 
-use structures::*;
-use util::*;
+use structures::{CommandCooldown, CommandFun, HandlerKind, MenuKind};
 
 #[proc_macro_attribute]
 pub fn slash_command(_attr: TokenStream, input: TokenStream) -> TokenStream {
 
     let func = parse_macro_input!(input as CommandFun);
 
-    let imports = &func.imports;
-    let name = &func.name;
-    let visibility = &func.visibility;
-    let args = &func.args;
-    let body = &func.body;
-    let is_async = &func.is_async;
-
-    let mut description = None;
-
-    for attr in &func.attrs {
-        if is_rustfmt_or_clippy_attr(&attr.path()) {
-            continue;
-        }
-
-
-        let values = propagate_err!(parse_values(attr));
-        let _span = values.span;
-
-        let name = values.name.to_string();
-        let name = &name[..];
-
-        match name {
-            "description" => if let Some(Lit::Str(lit_str)) = values.literals.get(0) {
-                description = Some(lit_str.value());
-            }
-            _ => {}
-        }
-    }
-
-    let description = description.map_or(quote!(), |desc| quote!(.description(#desc)));
-    let async_token = if *is_async == true {
-        quote!(async)
-    } else {
-        quote!()
-    };
-
-    let expanded =
-        quote! {
-        #visibility mod #name {
-            use serenity::{builder::CreateCommand};
-            #(use #imports;)*
-            #visibility fn register() -> CreateCommand {
-                CreateCommand::new(stringify!(#name))
-                    #description
-            }
-            
-            // #visibility fn run(_options: &[ResolvedOption]) -> String {
-            #visibility #async_token fn  run(#(#args),*) -> String {
-                #(#body)*
-            }
-        }
-    };
+    let expanded = propagate_err!(expand::command(&func));
 
     // dbg!(expanded.clone());
 
     TokenStream::from(expanded)
 }
+
+/// Turns a module into a command on the Apps menu of a user, named by the attribute, e.g.
+/// `#[user_command("Get IDs")]`.
+///
+/// It takes the same attributes as `#[slash_command]` apart from descriptions and options, and
+/// its `run` can take the `&User` and `Option<&PartialMember>` it's used on.
+#[proc_macro_attribute]
+pub fn user_command(attr: TokenStream, input: TokenStream) -> TokenStream {
+    context_menu(attr, input, MenuKind::User)
+}
+
+/// Turns a module into a command on the Apps menu of a message, named by the attribute, e.g.
+/// `#[message_command("Report message")]`.
+///
+/// It takes the same attributes as `#[slash_command]` apart from descriptions and options, and
+/// its `run` can take the `&Message` it's used on.
+#[proc_macro_attribute]
+pub fn message_command(attr: TokenStream, input: TokenStream) -> TokenStream {
+    context_menu(attr, input, MenuKind::Message)
+}
+
+fn context_menu(attr: TokenStream, input: TokenStream, kind: MenuKind) -> TokenStream {
+    let menu = parse_macro_input!(attr as LitStr);
+    let func = parse_macro_input!(input as CommandFun);
+
+    TokenStream::from(propagate_err!(expand::context_menu(&func, kind, &menu)))
+}
+
+/// Registers a function as the handler of the buttons and select menus whose `CustomId` has
+/// the given prefix, e.g. `#[component("meeting_reply")]`.
+///
+/// It's called as `handler(ctx: &Context, interaction: &ComponentInteraction, id: &CustomId)`.
+#[proc_macro_attribute]
+pub fn component(attr: TokenStream, input: TokenStream) -> TokenStream {
+    handler(attr, input, HandlerKind::Component)
+}
+
+/// Registers a function as the handler of the submitted modals whose `CustomId` has the given
+/// prefix, e.g. `#[modal("meeting_details")]`.
+///
+/// It's called as `handler(ctx: &Context, interaction: &ModalInteraction, id: &CustomId)`.
+#[proc_macro_attribute]
+pub fn modal(attr: TokenStream, input: TokenStream) -> TokenStream {
+    handler(attr, input, HandlerKind::Modal)
+}
+
+fn handler(attr: TokenStream, input: TokenStream, kind: HandlerKind) -> TokenStream {
+    let prefix = parse_macro_input!(attr as LitStr);
+    let func = parse_macro_input!(input as ItemFn);
+
+    TokenStream::from(propagate_err!(expand::handler(kind, &prefix, &func)))
+}
+
+/// Puts a prefix `#[command]` function on cooldown, e.g. `#[cooldown(user, 5)]`.
+///
+/// It has to go above `#[command]` so serenity never sees it, and can be repeated to use more
+/// than one bucket. `#[slash_command]` modules take the same attribute.
+#[proc_macro_attribute]
+pub fn cooldown(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let attr = TokenStream2::from(attr);
+    let attr: Attribute = parse_quote!(#[cooldown(#attr)]);
+    let values = propagate_err!(util::parse_values(&attr));
+    let cooldown = propagate_err!(CommandCooldown::from_values(&values));
+
+    let func = parse_macro_input!(input as ItemFn);
+
+    TokenStream::from(expand::prefix_cooldown(&cooldown, &func))
+}
+
+/// Derives `SlashOptions` and a `TryFrom<&[ResolvedOption]>` parser for a struct of options.
+///
+/// Use it on a `#[slash_command]` by marking the `run` argument with `#[options]`. The struct then
+/// declares all of the command's options, it can't be combined with `#[option(..)]` attributes.
+#[proc_macro_derive(
+    SlashOptions,
+    attributes(name, description, autocomplete, name_localized, description_localized)
+)]
+pub fn derive_slash_options(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    TokenStream::from(propagate_err!(derive::slash_options(&input)))
+}
+
+/// Derives `FromResolvedValue` for an enum, registering it as a string option with choices.
+#[proc_macro_derive(SlashChoice, attributes(name, description, name_localized))]
+pub fn derive_slash_choice(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    TokenStream::from(propagate_err!(derive::slash_choice(&input)))
+}
@@ -0,0 +1,248 @@
+use std::{ env, fs, path::PathBuf };
+
+use proc_macro2::{ Span, TokenStream as TokenStream2 };
+use quote::{ quote, ToTokens };
+use syn::{ parse::Error, Lit, LitStr, Result };
+use toml::{ Table, Value };
+
+use crate::attributes::{ ValueKind, Values };
+
+/// Locales Discord accepts for `name_localizations` and `description_localizations`.
+const LOCALES: &[&str] = &[
+    "id", "da", "de", "en-GB", "en-US", "es-ES", "es-419", "fr", "hr", "it", "lt", "hu", "nl", "no",
+    "pl", "pt-BR", "ro", "fi", "sv-SE", "vi", "tr", "cs", "el", "bg", "ru", "uk", "hi", "th",
+    "zh-CN", "ja", "zh-TW", "ko",
+];
+
+/// `Localizations` are the translated names and descriptions of a command, subcommand or
+/// option, as `(locale, text)` pairs.
+#[derive(Debug, Default)]
+pub struct Localizations {
+    pub names: Vec<(String, String)>,
+    pub descriptions: Vec<(String, String)>,
+}
+
+impl Localizations {
+    /// Adds the translation from a `#[name_localized("<locale>", "<name>")]` or
+    /// `#[description_localized("<locale>", "<description>")]` attribute, or from the same
+    /// attribute with the option name in front.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the attribute doesn't hold a known locale and a string.
+    pub fn push(&mut self, values: &Values, literals: &[Lit]) -> Result<()> {
+        let (Some(Lit::Str(locale)), Some(Lit::Str(text)), None) = (
+            literals.first(),
+            literals.get(1),
+            literals.get(2),
+        ) else {
+            return Err(
+                Error::new(
+                    values.span,
+                    format_args!("expected `#[{}(\"<locale>\", \"<text>\")]`", values.name)
+                )
+            );
+        };
+        check_locale(&locale.value(), locale.span())?;
+
+        let pair = (locale.value(), text.value());
+        if values.name.to_string().ends_with("name_localized") {
+            self.names.push(pair);
+        } else {
+            self.descriptions.push(pair);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the `name` and `description` of a catalog table in `locale`. The description has to
+    /// be there, the name can be left out to keep the default one.
+    fn push_table(&mut self, catalog: &Catalog, locale: &str, key: &str, table: &Table) -> Result<()> {
+        if let Some(name) = table.get("name") {
+            let Some(name) = name.as_str() else {
+                return Err(catalog.error(format_args!("`{locale}.{key}.name` should be a string")));
+            };
+            self.names.push((locale.to_owned(), name.to_owned()));
+        }
+
+        match table.get("description").map(Value::as_str) {
+            Some(Some(description)) => {
+                self.descriptions.push((locale.to_owned(), description.to_owned()));
+                Ok(())
+            }
+            Some(None) => {
+                Err(catalog.error(format_args!("`{locale}.{key}.description` should be a string")))
+            }
+            None => Err(catalog.missing(locale, &format!("{key}.description"))),
+        }
+    }
+}
+
+impl ToTokens for Localizations {
+    fn to_tokens(&self, stream: &mut TokenStream2) {
+        let names = self.names.iter().map(|(locale, name)| quote!(.name_localized(#locale, #name)));
+        let descriptions = self.descriptions
+            .iter()
+            .map(|(locale, description)| quote!(.description_localized(#locale, #description)));
+
+        stream.extend(quote!(#(#names)* #(#descriptions)*));
+    }
+}
+
+fn check_locale(locale: &str, span: Span) -> Result<()> {
+    if LOCALES.contains(&locale) {
+        Ok(())
+    } else {
+        Err(
+            Error::new(
+                span,
+                format_args!("unknown locale `{locale}`, expected one of: {}", LOCALES.join(", "))
+            )
+        )
+    }
+}
+
+/// `Catalog` is one module's part of a TOML translation catalog, in every locale the catalog
+/// has, loaded through `#[localizations("<path>")]` on a `#[slash_command]` module.
+///
+/// The catalog has a table per locale, holding a table per command:
+///
+/// ```toml
+/// [de.join_channel]
+/// name = "kanal_beitreten"
+/// description = "Lässt den Bot einem Sprachkanal beitreten"
+///
+/// [de.join_channel.options.channel]
+/// description = "Der Sprachkanal"
+/// ```
+///
+/// Subcommands are tables inside their parent's, next to its `name` and `description`.
+pub struct Catalog {
+    path: String,
+    span: Span,
+
+    /// Where this module sits in every locale's table, e.g. `create_meeting.user`.
+    key: String,
+    locales: Vec<(String, Table)>,
+}
+
+impl Catalog {
+    /// Loads the catalog at `path`, relative to the crate root, and picks out `command`.
+    ///
+    /// Also returns an `include_str!` of the file so the command is rebuilt when it changes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or parsed, has an unknown locale, or any
+    /// locale is missing `command`.
+    pub fn load(path: &LitStr, command: &str) -> Result<(Self, TokenStream2)> {
+        let full_path = env
+            ::var("CARGO_MANIFEST_DIR")
+            .map_or_else(|_| PathBuf::from(path.value()), |root| PathBuf::from(root).join(path.value()));
+
+        let source = fs
+            ::read_to_string(&full_path)
+            .map_err(|why| {
+                Error::new(path.span(), format_args!("can't read `{}`: {why}", full_path.display()))
+            })?;
+        let root = source
+            .parse::<Table>()
+            .map_err(|why| Error::new(path.span(), format_args!("can't parse `{}`: {why}", path.value())))?;
+
+        let catalog = Self {
+            path: path.value(),
+            span: path.span(),
+            key: String::new(),
+            locales: root
+                .into_iter()
+                .map(|(locale, table)| {
+                    check_locale(&locale, path.span())?;
+                    match table {
+                        Value::Table(table) => Ok((locale, table)),
+                        _ => Err(Error::new(path.span(), format_args!("`{locale}` should be a table"))),
+                    }
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        let full_path = full_path.display().to_string();
+        Ok((catalog.child(command)?, quote!(const _: &str = include_str!(#full_path);)))
+    }
+
+    /// Picks out the subcommand, or top-level command, called `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any locale is missing it.
+    pub fn child(&self, name: &str) -> Result<Self> {
+        let key = if self.key.is_empty() { name.to_owned() } else { format!("{}.{name}", self.key) };
+
+        let locales = self.locales
+            .iter()
+            .map(|(locale, table)| {
+                match table.get(name) {
+                    Some(Value::Table(child)) => Ok((locale.clone(), child.clone())),
+                    _ => Err(self.missing(locale, &key)),
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            path: self.path.clone(),
+            span: self.span,
+            key,
+            locales,
+        })
+    }
+
+    /// Adds this module's translations in every locale.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any locale is missing the description.
+    pub fn localize(&self, localizations: &mut Localizations) -> Result<()> {
+        for (locale, table) in &self.locales {
+            localizations.push_table(self, locale, &self.key, table)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the translations of this module's option called `option` in every locale.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any locale is missing the option or its description.
+    pub fn localize_option(&self, option: &str, localizations: &mut Localizations) -> Result<()> {
+        let key = format!("{}.options.{option}", self.key);
+
+        for (locale, table) in &self.locales {
+            let option_table = table
+                .get("options")
+                .and_then(Value::as_table)
+                .and_then(|options| options.get(option))
+                .and_then(Value::as_table)
+                .ok_or_else(|| self.missing(locale, &key))?;
+
+            localizations.push_table(self, locale, &key, option_table)?;
+        }
+
+        Ok(())
+    }
+
+    fn missing(&self, locale: &str, key: &str) -> Error {
+        self.error(format_args!("missing `{locale}.{key}` in `{}`", self.path))
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> Error {
+        Error::new(self.span, message)
+    }
+}
+
+/// Reads the path out of `#[localizations("<path>")]`.
+pub fn catalog_path(values: &Values) -> Result<LitStr> {
+    match (values.kind, values.literals.first()) {
+        (ValueKind::SingleList, Some(Lit::Str(path))) => Ok(path.clone()),
+        _ => Err(Error::new(values.span, "expected `#[localizations(\"<path>\")]`")),
+    }
+}
@@ -1,14 +1,20 @@
 use quote::{ quote, ToTokens };
-use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::{ Span, TokenStream as TokenStream2 };
 use syn::{
-    braced, parse::{ Error, Parse }, spanned::Spanned, token::Async, Attribute, Block, FnArg, Ident, Pat, Path, Result, ReturnType, Stmt, Token, UseTree, Visibility
+    parse::{ Error, Parse }, spanned::Spanned, Attribute, FnArg, Ident, Item, ItemFn, ItemMod, Lit, LitStr, Pat, Path, Result, ReturnType, Visibility
 };
 
-use crate::{ util::Parenthesised, Argument };
+use crate::{
+    attributes::{ ValueKind, Values },
+    localization::{ catalog_path, Localizations },
+    util::{ parse_values, Argument },
+    validate,
+};
 
 fn parse_argument(arg: FnArg) -> Result<Argument> {
     match arg {
         FnArg::Typed(typed) => {
+            let attrs = typed.attrs;
             let pat = typed.pat;
             let kind = typed.ty;
 
@@ -18,6 +24,7 @@ fn parse_argument(arg: FnArg) -> Result<Argument> {
                     let mutable = id.mutability;
 
                     Ok(Argument {
+                        attrs,
                         mutable,
                         name,
                         kind: *kind,
@@ -29,6 +36,7 @@ fn parse_argument(arg: FnArg) -> Result<Argument> {
                     let name = Ident::new("_", token.spans[0]);
 
                     Ok(Argument {
+                        attrs,
                         mutable: None,
                         name,
                         kind: *kind,
@@ -43,97 +51,944 @@ fn parse_argument(arg: FnArg) -> Result<Argument> {
     }
 }
 
+/// `CommandKind` is the level a `#[slash_command]` module, or a module nested inside one,
+/// sits at in the command tree.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CommandKind {
+    /// The top-level `#[slash_command]` module.
+    Command,
+
+    /// A nested module marked `#[subcommand]`.
+    SubCommand,
+
+    /// A nested module marked `#[subcommand_group]`, holding `#[subcommand]` modules.
+    SubCommandGroup,
+}
+
+impl CommandKind {
+    fn from_attrs(attrs: &[Attribute]) -> Option<Self> {
+        attrs.iter().find_map(|attr| {
+            if attr.path().is_ident("subcommand") {
+                Some(Self::SubCommand)
+            } else if attr.path().is_ident("subcommand_group") {
+                Some(Self::SubCommandGroup)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// `MenuKind` is what a context-menu command shows up on when right-clicked, set by using
+/// `#[user_command("..")]` or `#[message_command("..")]` instead of `#[slash_command]`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MenuKind {
+    User,
+    Message,
+}
+
+impl MenuKind {
+    /// The attribute the command is declared with.
+    pub const fn attribute(self) -> &'static str {
+        match self {
+            Self::User => "user_command",
+            Self::Message => "message_command",
+        }
+    }
+
+    /// The `CommandType` variant the command is registered as.
+    pub fn command_type(self) -> Ident {
+        let variant = match self {
+            Self::User => "User",
+            Self::Message => "Message",
+        };
+
+        Ident::new(variant, Span::call_site())
+    }
+}
+
+/// `HandlerKind` is what a function marked `#[component("..")]` or `#[modal("..")]` answers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HandlerKind {
+    Component,
+    Modal,
+}
+
+impl HandlerKind {
+    /// The attribute the handler is declared with.
+    pub const fn attribute(self) -> &'static str {
+        match self {
+            Self::Component => "component",
+            Self::Modal => "modal",
+        }
+    }
+
+    /// The registry entry the handler submits and the interaction it's called with.
+    pub fn types(self) -> (Ident, Ident) {
+        let (entry, interaction) = match self {
+            Self::Component => ("ComponentHandler", "ComponentInteraction"),
+            Self::Modal => ("ModalHandler", "ModalInteraction"),
+        };
+
+        (Ident::new(entry, Span::call_site()), Ident::new(interaction, Span::call_site()))
+    }
+}
+
+/// `CommandFun` is a `#[slash_command]` module, or a `#[subcommand]`/`#[subcommand_group]`
+/// module nested inside one.
 #[derive(Debug)]
 pub struct CommandFun {
     pub attrs: Vec<Attribute>,
-    pub imports: Vec<UseTree>,
+    pub kind: CommandKind,
     pub visibility: Visibility,
     pub name: Ident,
-    pub args: Vec<Argument>,
-    pub body: Vec<Stmt>,
-    pub is_async: bool,
+
+    /// Every other item in the module, `use`s, helper functions, consts and types, kept as
+    /// they were written.
+    pub items: Vec<Item>,
+
+    /// The entry function, which modules holding subcommands don't have.
+    pub run: Option<RunFn>,
+    pub subcommands: Vec<Self>,
+    pub autocompletes: Vec<AutocompleteHook>,
 }
 
 impl Parse for CommandFun {
     fn parse(stream: syn::parse::ParseStream) -> syn::Result<Self> {
-        let attrs = stream.call(Attribute::parse_outer)?;
-        stream.parse::<Token![mod]>()?;
+        Self::from_module(stream.parse()?, CommandKind::Command)
+    }
+}
+
+impl CommandFun {
+    fn from_module(module: ItemMod, kind: CommandKind) -> Result<Self> {
+        let ItemMod { attrs, vis: visibility, ident: name, content, .. } = module;
+        let Some((_, content)) = content else {
+            return Err(Error::new(name.span(), "expected the module's items inline, `mod name { .. }`"));
+        };
+
+        let mut items = Vec::new();
+        let mut functions = Vec::new();
+        let mut subcommands = Vec::new();
+        let mut autocompletes = Vec::new();
+        for item in content {
+            match item {
+                Item::Mod(module) => subcommands.push(Self::from_module_in(module, kind)?),
+                Item::Fn(mut func) => {
+                    if let Some(option) = AutocompleteHook::option_of(&func.attrs)? {
+                        func.attrs.retain(|attr| !attr.path().is_ident("autocomplete"));
+                        no_generics(&func)?;
+                        autocompletes.push(AutocompleteHook { option, func });
+                    } else {
+                        functions.push(func);
+                    }
+                }
+                item => items.push(item),
+            }
+        }
+
+        let entry = Self::entry_index(&functions, &name)?.map(|index| functions.remove(index));
+        items.extend(functions.into_iter().map(Item::Fn));
+
+        if !subcommands.is_empty() {
+            if let Some(func) = entry {
+                return Err(
+                    Error::new(
+                        func.sig.ident.span(),
+                        "a module holding subcommands can't have a `run` function of its own"
+                    )
+                );
+            }
+            if let Some(hook) = autocompletes.first() {
+                return Err(
+                    Error::new(
+                        hook.func.sig.ident.span(),
+                        "autocomplete hooks go in the subcommand holding the option"
+                    )
+                );
+            }
+        } else if kind == CommandKind::SubCommandGroup {
+            return Err(Error::new(name.span(), "expected at least one `#[subcommand]` module"));
+        }
+
+        let run = match entry {
+            Some(func) => Some(RunFn::new(func)?),
+            None if subcommands.is_empty() => {
+                return Err(
+                    Error::new(
+                        name.span(),
+                        format_args!("expected a `run` or `{name}` function, or one marked `#[run]`")
+                    )
+                );
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            attrs,
+            kind,
+            visibility,
+            name,
+            items,
+            run,
+            subcommands,
+            autocompletes,
+        })
+    }
+
+    /// Parses a module nested inside a module of `parent` kind, which has to be marked
+    /// `#[subcommand]` or `#[subcommand_group]`.
+    fn from_module_in(module: ItemMod, parent: CommandKind) -> Result<Self> {
+        let span = module.ident.span();
+        let kind = match (parent, CommandKind::from_attrs(&module.attrs)) {
+            (CommandKind::Command, Some(kind)) |
+            (CommandKind::SubCommandGroup, Some(kind @ CommandKind::SubCommand)) => kind,
+            (CommandKind::SubCommandGroup, _) => {
+                return Err(Error::new(span, "a subcommand group can only hold `#[subcommand]` modules"));
+            }
+            (CommandKind::SubCommand, _) => {
+                return Err(Error::new(span, "a subcommand can't hold nested modules"));
+            }
+            (CommandKind::Command, None) => {
+                return Err(
+                    Error::new(span, "nested modules must be marked `#[subcommand]` or `#[subcommand_group]`")
+                );
+            }
+        };
+
+        Self::from_module(module, kind)
+    }
+
+    /// Finds the entry function among the module's functions: the one marked `#[run]`, or else
+    /// the one called `run` or named after the module.
+    fn entry_index(functions: &[ItemFn], module: &Ident) -> Result<Option<usize>> {
+        let mut marked = functions
+            .iter()
+            .enumerate()
+            .filter(|(_, func)| func.attrs.iter().any(|attr| attr.path().is_ident("run")));
 
-        // Name of module if I want to use that instead
-        let name = stream.parse::<Ident>()?;
+        if let Some((index, _)) = marked.next() {
+            if let Some((_, second)) = marked.next() {
+                return Err(
+                    Error::new(second.sig.ident.span(), "only one function can be marked `#[run]`")
+                );
+            }
+
+            return Ok(Some(index));
+        }
+
+        Ok(
+            functions
+                .iter()
+                .position(|func| func.sig.ident == "run")
+                .or_else(|| functions.iter().position(|func| func.sig.ident == *module))
+        )
+    }
+}
 
-        let input;
-        braced!(input in stream);
+/// `RunFn` is the entry function of a module, called by the generated `invoke` with its
+/// arguments filled in from the interaction.
+#[derive(Debug)]
+pub struct RunFn {
+    /// The function as written, minus the `#[run]` and `#[options]` markers.
+    pub func: ItemFn,
+    pub args: Vec<Argument>,
+}
 
-        // ?fixme: Have to look into this, seems like macro used on import only gives import stream to proc_macro
-        let mut imports = Vec::new();
-        while input.peek(Token![use]) {
-            input.parse::<Token![use]>()?;
-            imports.push(input.parse()?);
-            input.parse::<Token![;]>()?;
+impl RunFn {
+    fn new(mut func: ItemFn) -> Result<Self> {
+        no_generics(&func)?;
+        if func.sig.output == ReturnType::Default {
+            return Err(
+                Error::new(
+                    func.sig.ident.span(),
+                    "expected a return type of `String`, `CommandResponse` or `Result<CommandResponse, CommandError>`"
+                )
+            );
         }
 
-        let visibility = input.parse::<Visibility>()?;
+        let args = func.sig.inputs
+            .iter()
+            .cloned()
+            .map(parse_argument)
+            .collect::<Result<Vec<_>>>()?;
 
-        // ?: Do I need this?
-        let is_async = if input.peek(Token![async]) {
-            input.parse::<Async>()?;
-            true
-        } else {
-            false
+        func.attrs.retain(|attr| !attr.path().is_ident("run"));
+        for input in &mut func.sig.inputs {
+            if let FnArg::Typed(typed) = input {
+                typed.attrs.retain(|attr| !attr.path().is_ident("options"));
+            }
+        }
+
+        Ok(Self { func, args })
+    }
+
+    pub const fn name(&self) -> &Ident {
+        &self.func.sig.ident
+    }
+
+    pub const fn is_async(&self) -> bool {
+        self.func.sig.asyncness.is_some()
+    }
+}
+
+/// The entry function and autocomplete hooks are called by generated code, which has no way to
+/// pick their generic parameters.
+fn no_generics(func: &ItemFn) -> Result<()> {
+    no_generics_in(func, "slash_command")
+}
+
+/// Checks that `func`, called by the code `#[attribute]` generates, isn't generic.
+///
+/// # Errors
+///
+/// Returns an error at the generics if it is.
+pub fn no_generics_in(func: &ItemFn, attribute: &str) -> Result<()> {
+    if func.sig.generics.params.is_empty() {
+        Ok(())
+    } else {
+        Err(
+            Error::new(
+                func.sig.generics.span(),
+                format_args!("`{}` is called by `#[{attribute}]` and can't be generic", func.sig.ident)
+            )
+        )
+    }
+}
+
+/// `AutocompleteHook` is a function marked `#[autocomplete(option = "<name>")]` next to `run`.
+///
+/// It is called as `hook(ctx: &Context, command: &CommandInteraction, value: &str)` whenever
+/// the user is typing into the named option and returns `Vec<AutocompleteChoice>`.
+#[derive(Debug)]
+pub struct AutocompleteHook {
+    pub option: String,
+    pub func: ItemFn,
+}
+
+impl AutocompleteHook {
+    /// Returns the option named by an `#[autocomplete(option = "..")]` attribute, if present.
+    fn option_of(attrs: &[Attribute]) -> Result<Option<String>> {
+        let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("autocomplete")) else {
+            return Ok(None);
         };
-        // let async_is = input.parse::<Asyn
 
-        input.parse::<Token![fn]>()?;
+        let mut option = None;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("option") {
+                option = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `option = \"<name>\"`"))
+            }
+        })?;
 
-        // let name = input.parse()?;
-        input.parse::<Ident>()?;
+        option
+            .map(Some)
+            .ok_or_else(|| Error::new(attr.span(), "expected `#[autocomplete(option = \"<name>\")]`"))
+    }
+}
+
+impl ToTokens for CommandFun {
+    fn to_tokens(&self, stream: &mut TokenStream2) {
+        let Self { items, run, .. } = self;
+        let run = run.as_ref().map(|run| &run.func);
+
+        stream.extend(quote! {
+            #(#items)*
+            #run
+        });
+    }
+}
+
+/// `CommandAttrs` holds everything declared through attributes on a `#[slash_command]` module.
+#[derive(Debug, Default)]
+pub struct CommandAttrs {
+    pub description: Option<String>,
+    pub options: Vec<CommandOption>,
+
+    /// The `Defer` variant named by `#[defer(..)]`, if present.
+    pub defer: Option<TokenStream2>,
+
+    /// The `Permissions` named by `#[default_member_permissions(..)]`.
+    pub permissions: Vec<Ident>,
+
+    /// Set by `#[guild_only]`.
+    pub guild_only: bool,
+
+    /// Set by `#[dm_permission]` or `#[dm_permission(false)]`.
+    pub dm_permission: Option<bool>,
+
+    /// Set by `#[nsfw]`.
+    pub nsfw: bool,
+
+    /// Set by `#[owners_only]`.
+    pub owners_only: bool,
+
+    /// Every `#[cooldown(..)]` on the module.
+    pub cooldowns: Vec<CommandCooldown>,
+
+    /// Translations from `#[name_localized(..)]` and `#[description_localized(..)]`.
+    pub localizations: Localizations,
+
+    /// The catalog path from `#[localizations("..")]`.
+    pub catalog: Option<LitStr>,
 
-        let Parenthesised(args) = input.parse::<Parenthesised<FnArg>>()?;
+    /// The `/help` category from `#[category("..")]`.
+    pub category: Option<String>,
+}
+
+impl CommandAttrs {
+    /// Collects the known attributes of a `#[slash_command]` module.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an attribute can't be parsed or has the wrong shape.
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut command = Self::default();
+        let mut option_localizations = Vec::new();
+        let mut dm_permission_span = None;
+
+        for attr in attrs {
+            if is_rustfmt_or_clippy_attr(attr.path()) {
+                continue;
+            }
+
+            let values = parse_values(attr)?;
 
-        let _ret = match input.parse::<ReturnType>()? {
-            ReturnType::Type(_, t) => (*t).clone(),
-            ReturnType::Default => {
+            match values.name.to_string().as_str() {
+                "description" => if let Some(Lit::Str(lit_str)) = values.literals.first() {
+                    validate::description(&lit_str.value(), lit_str.span())?;
+                    command.description = Some(lit_str.value());
+                }
+                "option" => command.options.push(CommandOption::from_values(&values)?),
+                "defer" => command.defer = Some(defer_variant(&values)?),
+                "default_member_permissions" => {
+                    command.permissions.extend(permission_names(&values)?);
+                }
+                "guild_only" => command.guild_only = flag(&values)?,
+                "dm_permission" => {
+                    command.dm_permission = Some(flag(&values)?);
+                    dm_permission_span = Some(values.span);
+                }
+                "nsfw" => command.nsfw = flag(&values)?,
+                "owners_only" => command.owners_only = flag(&values)?,
+                "cooldown" => command.cooldowns.push(CommandCooldown::from_values(&values)?),
+                "name_localized" | "description_localized" => {
+                    command.localizations.push(&values, &values.literals)?;
+                }
+                "option_name_localized" | "option_description_localized" => {
+                    option_localizations.push(values);
+                }
+                "localizations" => command.catalog = Some(catalog_path(&values)?),
+                "category" => match (values.kind, values.literals.first()) {
+                    (ValueKind::SingleList, Some(Lit::Str(category))) => {
+                        command.category = Some(category.value());
+                    }
+                    _ => {
+                        return Err(Error::new(values.span, "expected `#[category(\"<name>\")]`"));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Options can be declared after their translations, so these are matched up last
+        for values in option_localizations {
+            let Some((Lit::Str(option), literals)) = values.literals.split_first() else {
+                return Err(
+                    Error::new(
+                        values.span,
+                        format_args!(
+                            "expected `#[{}(\"<option>\", \"<locale>\", \"<text>\")]`",
+                            values.name
+                        )
+                    )
+                );
+            };
+            let Some(declared) = command.options.iter_mut().find(|declared| declared.name == option.value()) else {
                 return Err(
-                    input.error("expected a result type of either `CommandResult` or `CheckResult`")
+                    Error::new(option.span(), format_args!("no `#[option]` called `{}`", option.value()))
+                );
+            };
+
+            declared.localizations.push(&values, literals)?;
+        }
+
+        if let (true, Some(true), Some(span)) = (command.guild_only, command.dm_permission, dm_permission_span) {
+            return Err(
+                Error::new(
+                    span,
+                    "`#[guild_only]` and `#[dm_permission]` can't be used together"
+                )
+            );
+        }
+
+        Ok(command)
+    }
+
+    /// Returns the first attribute that only means something on the top-level
+    /// `#[slash_command]` module, if any is set.
+    pub fn top_level_attr(&self) -> Option<&'static str> {
+        [
+            ("defer", self.defer.is_some()),
+            ("default_member_permissions", !self.permissions.is_empty()),
+            ("guild_only", self.guild_only),
+            ("dm_permission", self.dm_permission.is_some()),
+            ("nsfw", self.nsfw),
+            ("owners_only", self.owners_only),
+            ("cooldown", !self.cooldowns.is_empty()),
+            ("localizations", self.catalog.is_some()),
+            ("category", self.category.is_some()),
+        ]
+            .into_iter()
+            .find_map(|(name, set)| set.then_some(name))
+    }
+
+    /// Returns the declared option called `name`, if any.
+    pub fn option(&self, name: &str) -> Option<&CommandOption> {
+        self.options.iter().find(|option| option.name == name)
+    }
+}
+
+/// `CommandCooldown` is a cooldown declared with `#[cooldown(<user|guild|channel>, <seconds>)]`.
+#[derive(Debug)]
+pub struct CommandCooldown {
+    /// The `Bucket` variant.
+    pub bucket: Ident,
+    pub seconds: u64,
+}
+
+impl CommandCooldown {
+    /// Builds a `CommandCooldown` out of the values of a `#[cooldown(..)]` attribute.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bucket is unknown or the duration isn't a whole number of seconds.
+    pub fn from_values(values: &Values) -> Result<Self> {
+        let (Some(Lit::Str(bucket)), Some(Lit::Int(seconds)), None) = (
+            values.literals.first(),
+            values.literals.get(1),
+            values.literals.get(2),
+        ) else {
+            return Err(
+                Error::new(values.span, "expected `#[cooldown(<user|guild|channel>, <seconds>)]`")
+            );
+        };
+
+        let variant = match bucket.value().as_str() {
+            "user" => "User",
+            "guild" => "Guild",
+            "channel" => "Channel",
+            _ => {
+                return Err(
+                    Error::new(bucket.span(), "unknown bucket, expected `user`, `guild` or `channel`")
                 );
             }
         };
 
-        let bcont;
-        braced!(bcont in input);
-        let body = bcont.call(Block::parse_within)?;
+        Ok(Self {
+            bucket: Ident::new(variant, bucket.span()),
+            seconds: seconds.base10_parse()?,
+        })
+    }
+}
 
-        let args = args.into_iter().map(parse_argument).collect::<Result<Vec<_>>>()?;
+impl ToTokens for CommandCooldown {
+    fn to_tokens(&self, stream: &mut TokenStream2) {
+        let Self { bucket, seconds } = self;
 
-        Ok(CommandFun {
-            attrs,
-            imports,
-            visibility,
+        stream.extend(
+            quote! {
+            crate::cooldown::Cooldown {
+                bucket: crate::cooldown::Bucket::#bucket,
+                duration: std::time::Duration::from_secs(#seconds),
+            }
+        }
+        );
+    }
+}
+
+/// Reads a flag attribute, either `#[flag]` or `#[flag(true|false)]`.
+fn flag(values: &Values) -> Result<bool> {
+    match (values.kind, values.literals.first()) {
+        (ValueKind::Name, _) => Ok(true),
+        (ValueKind::SingleList, Some(Lit::Bool(value))) => Ok(value.value),
+        _ => {
+            Err(
+                Error::new(
+                    values.span,
+                    format_args!("expected `#[{0}]` or `#[{0}(true|false)]`", values.name)
+                )
+            )
+        }
+    }
+}
+
+/// Reads the `Permissions` names out of `#[default_member_permissions(..)]`.
+fn permission_names(values: &Values) -> Result<Vec<Ident>> {
+    if !matches!(values.kind, ValueKind::SingleList | ValueKind::List) {
+        return Err(
+            Error::new(
+                values.span,
+                "expected permission names, e.g. `#[default_member_permissions(MANAGE_CHANNELS)]`"
+            )
+        );
+    }
+
+    values.literals
+        .iter()
+        .map(|lit| {
+            match lit {
+                Lit::Str(name) => Ok(Ident::new(&name.value(), name.span())),
+                _ => Err(Error::new(lit.span(), "expected a permission name, e.g. `MANAGE_CHANNELS`")),
+            }
+        })
+        .collect()
+}
+
+/// Maps `#[defer]` to `Defer::Ephemeral`, `#[defer(public)]` to `Defer::Public` and
+/// `#[defer(on_budget[, public])]` to `Defer::OnBudget`.
+fn defer_variant(values: &Values) -> Result<TokenStream2> {
+    if values.kind == ValueKind::Name {
+        return Ok(quote!(Ephemeral));
+    }
+
+    let flags: Vec<String> = values.literals
+        .iter()
+        .map(|lit| match lit {
+            Lit::Str(flag) => flag.value(),
+            _ => String::new(),
+        })
+        .collect();
+
+    match flags.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["public"] => Ok(quote!(Public)),
+        ["on_budget"] => Ok(quote!(OnBudget { ephemeral: true })),
+        ["on_budget", "public"] => Ok(quote!(OnBudget { ephemeral: false })),
+        _ => {
+            Err(
+                Error::new(
+                    values.span,
+                    "expected `#[defer]`, `#[defer(public)]` or `#[defer(on_budget[, public])]`"
+                )
+            )
+        }
+    }
+}
+
+/// `CommandOption` is a single slash command option declared with
+/// `#[option(<kind>, "<name>", "<description>"[, required])]`.
+#[derive(Debug)]
+pub struct CommandOption {
+    pub kind: Ident,
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+
+    /// Set when an `#[autocomplete(option = "..")]` hook exists for this option.
+    pub autocomplete: bool,
+
+    /// Translations from `#[option_name_localized(..)]`, `#[option_description_localized(..)]`
+    /// and the command's catalog.
+    pub localizations: Localizations,
+
+    /// Where the option was declared, for errors about it.
+    pub span: Span,
+}
+
+impl CommandOption {
+    /// Builds a `CommandOption` out of the values of an `#[option(..)]` attribute.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kind is not one of Discord's option types, if the name or
+    /// description are not string literals, or if an unknown flag is passed.
+    pub fn from_values(values: &Values) -> Result<Self> {
+        if values.kind != ValueKind::List {
+            return Err(
+                Error::new(values.span, format_args!("expected {}", ValueKind::List))
+            );
+        }
+
+        let mut literals = values.literals.iter();
+
+        let kind = match literals.next() {
+            Some(Lit::Str(kind)) => Ident::new(&kind.value(), kind.span()),
+            _ => {
+                return Err(Error::new(values.span, "expected the option kind, e.g. `channel`"));
+            }
+        };
+
+        if option_type(&kind).is_none() {
+            return Err(
+                Error::new(
+                    kind.span(),
+                    format_args!(
+                        "unknown option kind `{kind}`, expected one of: {}",
+                        OPTION_KINDS.iter()
+                            .map(|(kind, _)| *kind)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                )
+            );
+        }
+
+        let (name, description) = match (literals.next(), literals.next()) {
+            (Some(Lit::Str(name)), Some(Lit::Str(description))) => {
+                validate::name(&name.value(), name.span())?;
+                validate::description(&description.value(), description.span())?;
+                (name.value(), description.value())
+            }
+            _ => {
+                return Err(
+                    Error::new(values.span, "expected the option name and description as strings")
+                );
+            }
+        };
+
+        let mut required = false;
+        for flag in literals {
+            match flag {
+                Lit::Str(flag) if flag.value() == "required" => required = true,
+                _ => {
+                    return Err(Error::new(flag.span(), "unknown option flag, expected `required`"));
+                }
+            }
+        }
+
+        Ok(Self {
+            kind,
             name,
-            args,
-            body,
-            is_async,
+            description,
+            required,
+            autocomplete: false,
+            localizations: Localizations::default(),
+            span: values.span,
         })
     }
 }
 
-impl ToTokens for CommandFun {
+impl ToTokens for CommandOption {
     fn to_tokens(&self, stream: &mut TokenStream2) {
-        let Self { attrs: _, imports, visibility, name, args, body, is_async: _ } = self;
+        let Self { kind, name, description, required, autocomplete, localizations, .. } = self;
+        let option_type = option_type(kind).unwrap_or_else(|| kind.clone());
+        let autocomplete = autocomplete.then(|| quote!(.set_autocomplete(true)));
 
         stream.extend(
             quote! {
-            #(use #imports;)*
-            #visibility fn #name(#(#args),*) {
-                #(#body)*
-            }
+            serenity::builder::CreateCommandOption::new(
+                serenity::all::CommandOptionType::#option_type,
+                #name,
+                #description
+            ).required(#required)#autocomplete #localizations
         }
-        )
+        );
     }
 }
 
-#[allow(unused)]
+/// Option kinds accepted by `#[option(..)]` and the `CommandOptionType` variant they map to.
+const OPTION_KINDS: &[(&str, &str)] = &[
+    ("string", "String"),
+    ("integer", "Integer"),
+    ("number", "Number"),
+    ("boolean", "Boolean"),
+    ("user", "User"),
+    ("channel", "Channel"),
+    ("role", "Role"),
+    ("mentionable", "Mentionable"),
+    ("attachment", "Attachment"),
+];
+
+fn option_type(kind: &Ident) -> Option<Ident> {
+    OPTION_KINDS.iter()
+        .find(|(name, _)| kind == name)
+        .map(|(_, variant)| Ident::new(variant, kind.span()))
+}
+
 pub fn is_rustfmt_or_clippy_attr(path: &Path) -> bool {
-    path.segments.first().map_or(false, |s| (s.ident == "rustfmt" || s.ident == "clippy"))
+    path.segments.first().is_some_and(|s| s.ident == "rustfmt" || s.ident == "clippy")
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use syn::{ parse_quote, FnArg, Item };
+
+    use super::{ CommandFun, CommandKind };
+
+    fn parse(tokens: proc_macro2::TokenStream) -> syn::Result<CommandFun> {
+        syn::parse2(tokens)
+    }
+
+    #[test]
+    fn parses_sync_module() {
+        let func = parse(
+            quote! {
+                mod ping {
+                    use serenity::all::ResolvedOption;
+
+                    pub fn run(_options: &[ResolvedOption]) -> String {
+                        "Pong!".to_owned()
+                    }
+                }
+            }
+        ).expect("should parse");
+
+        assert_eq!(func.name, "ping");
+        assert_eq!(func.kind, CommandKind::Command);
+        assert!(matches!(func.items.as_slice(), [Item::Use(_)]));
+
+        let run = func.run.expect("should find run");
+        assert_eq!(run.name(), "run");
+        assert!(!run.is_async());
+        assert_eq!(run.args.len(), 1);
+    }
+
+    #[test]
+    fn parses_async_module() {
+        let func = parse(
+            quote! {
+                mod id {
+                    pub async fn run(ctx: &Context, guild_id: GuildId, #[options] args: IdOptions) -> String {
+                        String::new()
+                    }
+                }
+            }
+        ).expect("should parse");
+
+        let run = func.run.expect("should find run");
+        assert!(run.is_async());
+        assert_eq!(run.args.len(), 3);
+        assert!(run.args[2].attrs.iter().any(|attr| attr.path().is_ident("options")));
+
+        // `#[options]` only means something to the macro, so it's gone from the function itself
+        let FnArg::Typed(options) = &run.func.sig.inputs[2] else {
+            panic!("expected a typed argument");
+        };
+        assert!(options.attrs.is_empty());
+    }
+
+    #[test]
+    fn finds_run_by_module_name() {
+        let func = parse(
+            quote! {
+                mod ping {
+                    pub fn ping() -> String {
+                        "Pong!".to_owned()
+                    }
+                }
+            }
+        ).expect("should parse");
+
+        assert_eq!(func.run.expect("should find run").name(), "ping");
+    }
+
+    #[test]
+    fn finds_run_by_attribute_and_keeps_helpers() {
+        let func = parse(
+            quote! {
+                mod ping {
+                    const GREETING: &str = "Pong!";
+
+                    struct Reply;
+
+                    fn run() -> usize {
+                        0
+                    }
+
+                    #[run]
+                    pub fn respond() -> String {
+                        helper()
+                    }
+
+                    fn helper() -> String {
+                        GREETING.to_owned()
+                    }
+
+                    #[autocomplete(option = "name")]
+                    fn suggest(ctx: &Context, command: &CommandInteraction, value: &str) -> Vec<AutocompleteChoice> {
+                        Vec::new()
+                    }
+                }
+            }
+        ).expect("should parse");
+
+        let run = func.run.expect("should find run");
+        assert_eq!(run.name(), "respond");
+        assert!(run.func.attrs.is_empty());
+
+        assert_eq!(func.items.len(), 4);
+        assert_eq!(func.autocompletes.len(), 1);
+        assert_eq!(func.autocompletes[0].option, "name");
+        assert!(func.autocompletes[0].func.attrs.is_empty());
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        let func = parse(
+            quote! {
+                mod meeting {
+                    fn shared() {}
+
+                    #[subcommand]
+                    mod user {
+                        pub fn run(user: UserId) -> String {
+                            String::new()
+                        }
+                    }
+
+                    #[subcommand_group]
+                    mod admin {
+                        #[subcommand]
+                        mod reset {
+                            pub async fn run() -> String {
+                                String::new()
+                            }
+                        }
+                    }
+                }
+            }
+        ).expect("should parse");
+
+        assert!(func.run.is_none());
+        assert_eq!(func.items.len(), 1);
+        assert_eq!(func.subcommands.len(), 2);
+        assert_eq!(func.subcommands[0].kind, CommandKind::SubCommand);
+        assert_eq!(func.subcommands[1].kind, CommandKind::SubCommandGroup);
+        assert_eq!(func.subcommands[1].subcommands[0].kind, CommandKind::SubCommand);
+    }
+
+    #[test]
+    fn rejects_malformed_modules() {
+        let cases = [
+            quote!(mod ping {}),
+            quote!(mod ping;),
+            quote!(mod ping { fn run() {} }),
+            quote!(mod ping { fn run<T>() -> String { String::new() } }),
+            quote!(mod ping { #[run] fn a() -> String { String::new() } #[run] fn b() -> String { String::new() } }),
+            quote!(mod ping { mod nested { fn run() -> String { String::new() } } }),
+            quote!(mod ping { fn run() -> String { String::new() } #[subcommand] mod sub { fn run() -> String { String::new() } } }),
+            quote!(mod ping { #[subcommand_group] mod group {} }),
+        ];
+
+        for case in cases {
+            assert!(parse(case.clone()).is_err(), "`{case}` should be rejected");
+        }
+    }
+
+    #[test]
+    fn accepts_use_items_anywhere() {
+        let func: CommandFun = parse_quote! {
+            mod ping {
+                pub fn run() -> Reply {
+                    Reply
+                }
+
+                use crate::Reply;
+            }
+        };
+
+        assert!(matches!(func.items.as_slice(), [Item::Use(_)]));
+    }
 }
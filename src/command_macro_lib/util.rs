@@ -1,13 +1,14 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{ quote, ToTokens };
-use syn::parse::{ Error, Parse, ParseStream, Parser, Result as SynResult };
+use syn::parse::{ Error, Parser, Result as SynResult };
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
-use syn::token::{ Comma, Mut };
-use syn::{ parenthesized, Attribute, Expr, Ident, Lit, LitStr, Meta, Path, Token, Type };
+use syn::token::Mut;
+use syn::{ Attribute, Expr, GenericArgument, Ident, Lit, LitStr, Meta, Path, PathArguments, Token, Type };
 
 use crate::attributes::{ ValueKind, Values };
+use crate::structures::MenuKind;
 
 #[inline]
 pub fn into_stream(e: &Error) -> TokenStream {
@@ -49,10 +50,12 @@ macro_rules! propagate_err {
 ///
 /// # Example
 ///
-/// ```rust
-/// let path = syn::parse_str::<syn::Path>("my_ident").unwrap();
-/// let ident = to_ident(&path).unwrap();
-/// assert_eq!(ident, syn::Ident::new("my_ident", proc_macro2::Span::call_site()));
+/// This is private to the macro crate, so the example is checked by the unit tests instead.
+///
+/// ```ignore
+/// let path: syn::Path = syn::parse_quote!(my_ident);
+/// let ident = to_ident(&path)?;
+/// assert_eq!(ident, "my_ident");
 /// ```
 #[allow(unused)]
 fn to_ident(p: &Path) -> SynResult<Ident> {
@@ -98,10 +101,12 @@ fn to_ident(p: &Path) -> SynResult<Ident> {
 ///
 /// # Example
 ///
-/// ```rust
-/// let attr = syn::parse_str::<syn::Attribute>("#[my_attr = \"my_value\"]").unwrap();
-/// let values = parse_values(&attr).unwrap();
-/// assert_eq!(values.name, syn::Ident::new("my_attr", proc_macro2::Span::call_site()));
+/// This is private to the macro crate, so the example is checked by the unit tests instead.
+///
+/// ```ignore
+/// let attr: syn::Attribute = syn::parse_quote!(#[my_attr = "my_value"]);
+/// let values = parse_values(&attr)?;
+/// assert_eq!(values.name, "my_attr");
 /// assert_eq!(values.kind, ValueKind::Equals);
 /// assert_eq!(values.literals.len(), 1);
 /// ```
@@ -125,11 +130,8 @@ pub fn parse_values(attr: &Attribute) -> SynResult<Values> {
                 meta.tokens.clone()
             );
 
-            let nested = match nested {
-                Ok(nested) => nested,
-                Err(_) => {
-                    return Err(Error::new(attr.span(), "list cannot be empty"));
-                }
+            let Ok(nested) = nested else {
+                return Err(Error::new(attr.span(), "list cannot be empty"));
             };
 
             let mut lits = Vec::with_capacity(nested.len());
@@ -178,20 +180,11 @@ pub fn parse_values(attr: &Attribute) -> SynResult<Values> {
     }
 }
 
-#[derive(Debug)]
-pub struct Parenthesised<T>(pub Punctuated<T, Comma>);
-
-impl<T: Parse> Parse for Parenthesised<T> {
-    fn parse(input: ParseStream<'_>) -> SynResult<Self> {
-        let content;
-        parenthesized!(content in input);
-
-        Ok(Parenthesised(content.parse_terminated(T::parse, Comma)?))
-    }
-}
-
 #[derive(Debug)]
 pub struct Argument {
+    /// Attributes on the argument itself, such as `#[options]`. These are consumed by the macro
+    /// and never emitted.
+    pub attrs: Vec<Attribute>,
     pub mutable: Option<Mut>,
     pub name: Ident,
     pub kind: Type,
@@ -199,10 +192,225 @@ pub struct Argument {
 
 impl ToTokens for Argument {
     fn to_tokens(&self, stream: &mut TokenStream2) {
-        let Argument { mutable, name, kind } = self;
+        let Self { attrs: _, mutable, name, kind } = self;
 
         stream.extend(quote! {
             #mutable #name: #kind
         });
     }
 }
+
+/// `ArgSource` is where the registry's `invoke` wrapper takes a `run` argument from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArgSource {
+    /// `&Context`
+    Context,
+
+    /// `GuildId`, only available when the command is used in a guild.
+    GuildId,
+
+    /// `&CommandInteraction`
+    Interaction,
+
+    /// `&[ResolvedOption]`
+    Options,
+
+    /// `&User`, the user a `#[user_command]` is used on.
+    TargetUser,
+
+    /// `Option<&PartialMember>`, the member a `#[user_command]` is used on, if it is used in a
+    /// guild.
+    TargetMember,
+
+    /// `&Message`, the message a `#[message_command]` is used on.
+    TargetMessage,
+}
+
+impl ArgSource {
+    /// The kind of context-menu command passed this argument, if only those are.
+    pub const fn menu(self) -> Option<MenuKind> {
+        match self {
+            Self::TargetUser | Self::TargetMember => Some(MenuKind::User),
+            Self::TargetMessage => Some(MenuKind::Message),
+            Self::Context | Self::GuildId | Self::Interaction | Self::Options => None,
+        }
+    }
+}
+
+impl Argument {
+    /// Works out which `ArgSource` fills this argument, based on its type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the type is not one the `invoke` wrapper knows how to provide.
+    pub fn source(&self) -> SynResult<ArgSource> {
+        let (is_ref, ty) = match &self.kind {
+            Type::Reference(reference) => (true, &*reference.elem),
+            ty => (false, ty),
+        };
+
+        let source = match ty {
+            Type::Slice(slice) if is_ref && last_ident_is(&slice.elem, "ResolvedOption") => {
+                Some(ArgSource::Options)
+            }
+            ty if is_ref && last_ident_is(ty, "Context") => Some(ArgSource::Context),
+            ty if is_ref && last_ident_is(ty, "CommandInteraction") => {
+                Some(ArgSource::Interaction)
+            }
+            ty if !is_ref && last_ident_is(ty, "GuildId") => Some(ArgSource::GuildId),
+            ty if is_ref && last_ident_is(ty, "User") => Some(ArgSource::TargetUser),
+            ty if is_ref && last_ident_is(ty, "Message") => Some(ArgSource::TargetMessage),
+            ty if !is_ref && option_of(ty).is_some_and(|inner| {
+                matches!(inner, Type::Reference(member) if last_ident_is(&member.elem, "PartialMember"))
+            }) => Some(ArgSource::TargetMember),
+            _ => None,
+        };
+
+        source.ok_or_else(|| {
+            Error::new(
+                self.kind.span(),
+                "unsupported argument, expected one of `&Context`, `GuildId`, `&CommandInteraction`, `&[ResolvedOption]`, a declared option, or the `&User`, `Option<&PartialMember>` or `&Message` a context-menu command is used on"
+            )
+        })
+    }
+}
+
+fn last_ident_is(ty: &Type, ident: &str) -> bool {
+    match ty {
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| segment.ident == ident),
+        _ => false,
+    }
+}
+
+/// Returns `T` if `ty` is an `Option<T>`.
+fn option_of(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last().filter(|segment| segment.ident == "Option")?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    match args.args.first() {
+        Some(GenericArgument::Type(inner)) => Some(inner),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{ parse_quote, Attribute, Lit, Path };
+
+    use super::{ parse_values, to_ident };
+    use crate::attributes::ValueKind;
+
+    fn strings(literals: &[Lit]) -> Vec<String> {
+        literals
+            .iter()
+            .map(|lit| match lit {
+                Lit::Str(lit) => lit.value(),
+                Lit::Int(lit) => lit.to_string(),
+                Lit::Bool(lit) => lit.value.to_string(),
+                _ => panic!("unexpected literal"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_name() {
+        let attr: Attribute = parse_quote!(#[guild_only]);
+        let values = parse_values(&attr).expect("should parse");
+
+        assert_eq!(values.name, "guild_only");
+        assert_eq!(values.kind, ValueKind::Name);
+        assert!(values.literals.is_empty());
+    }
+
+    #[test]
+    fn parses_equals() {
+        let attr: Attribute = parse_quote!(#[description = "It Pings"]);
+        let values = parse_values(&attr).expect("should parse");
+
+        assert_eq!(values.name, "description");
+        assert_eq!(values.kind, ValueKind::Equals);
+        assert_eq!(strings(&values.literals), ["It Pings"]);
+    }
+
+    #[test]
+    fn parses_single_list() {
+        let attr: Attribute = parse_quote!(#[description("It Pings")]);
+        let values = parse_values(&attr).expect("should parse");
+
+        assert_eq!(values.kind, ValueKind::SingleList);
+        assert_eq!(strings(&values.literals), ["It Pings"]);
+    }
+
+    #[test]
+    fn parses_list_of_literals_and_identifiers() {
+        let attr: Attribute = parse_quote!(#[option(user, "user", "The user", required)]);
+        let values = parse_values(&attr).expect("should parse");
+
+        assert_eq!(values.name, "option");
+        assert_eq!(values.kind, ValueKind::List);
+        assert_eq!(strings(&values.literals), ["user", "user", "The user", "required"]);
+    }
+
+    #[test]
+    fn parses_mixed_literal_kinds() {
+        let attr: Attribute = parse_quote!(#[cooldown(user, 30)]);
+        let values = parse_values(&attr).expect("should parse");
+
+        assert_eq!(values.kind, ValueKind::List);
+        assert!(matches!(values.literals.as_slice(), [Lit::Str(_), Lit::Int(_)]));
+
+        let attr: Attribute = parse_quote!(#[dm_permission(false)]);
+        let values = parse_values(&attr).expect("should parse");
+
+        assert_eq!(values.kind, ValueKind::SingleList);
+        assert_eq!(strings(&values.literals), ["false"]);
+    }
+
+    #[test]
+    fn rejects_nested_lists() {
+        let attr: Attribute = parse_quote!(#[option(user, ("a", "b"))]);
+
+        assert!(parse_values(&attr).is_err());
+    }
+
+    #[test]
+    fn rejects_paths_with_segments() {
+        let attr: Attribute = parse_quote!(#[default_member_permissions(Permissions::ADMINISTRATOR)]);
+
+        assert!(parse_values(&attr).is_err());
+
+        let attr: Attribute = parse_quote!(#[serenity::description]);
+
+        assert!(parse_values(&attr).is_err());
+    }
+
+    #[test]
+    fn rejects_non_literal_values() {
+        let attr: Attribute = parse_quote!(#[description = concat!("a", "b")]);
+
+        assert!(parse_values(&attr).is_err());
+    }
+
+    #[test]
+    fn converts_single_segment_paths() {
+        let path: Path = parse_quote!(my_ident);
+
+        assert_eq!(to_ident(&path).expect("should convert"), "my_ident");
+    }
+
+    #[test]
+    fn rejects_longer_paths() {
+        let path: Path = parse_quote!(a::b);
+
+        assert!(to_ident(&path).is_err());
+
+        let path: Path = parse_quote!(Vec<u8>);
+
+        assert!(to_ident(&path).is_err());
+    }
+}
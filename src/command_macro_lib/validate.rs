@@ -0,0 +1,285 @@
+use proc_macro2::Span;
+use syn::{ parse::Error, LitStr, Result };
+
+use crate::expand::is_options_struct;
+use crate::localization::Localizations;
+use crate::structures::{ CommandAttrs, CommandFun, CommandOption, MenuKind };
+
+/// The longest name Discord accepts for a command, subcommand or option.
+pub const MAX_NAME_LENGTH: usize = 32;
+
+/// The longest description Discord accepts for a command, subcommand or option.
+pub const MAX_DESCRIPTION_LENGTH: usize = 100;
+
+/// The longest `custom_id` prefix a `#[component("..")]` or `#[modal("..")]` can have, which
+/// leaves the rest of the 100 characters of a `custom_id` for its expiry and payload.
+pub const MAX_PREFIX_LENGTH: usize = 32;
+
+/// The most options, or subcommands, Discord accepts at a single level of a command.
+pub const MAX_OPTIONS: usize = 25;
+
+/// Checks a module of a `#[slash_command]` against Discord's rules, so a command Discord would
+/// reject fails the build instead of `set_commands` once the bot is running.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid, the description is missing, there are too many
+/// options or subcommands, a required option comes after an optional one, or options are
+/// declared both by `#[option]` attributes and an `#[options]` struct.
+pub fn command(func: &CommandFun, command: &CommandAttrs) -> Result<()> {
+    let span = func.name.span();
+    name(&func.name.to_string(), span)?;
+
+    if command.description.is_none() {
+        return Err(Error::new(span, "missing `#[description(\"..\")]` for this command"));
+    }
+    localizations(&command.localizations, span)?;
+
+    // Discord only takes either options or subcommands on the same level
+    if let (Some(option), Some(_)) = (command.options.first(), func.subcommands.first()) {
+        return Err(
+            Error::new(option.span, "a command with subcommands can't have options, declare them on its subcommands")
+        );
+    }
+
+    // The struct's options are only known once it's derived, so they can't be checked against
+    // the attributes' for their count and order: one or the other declares all of them
+    let mut structs = func.run.iter().flat_map(|run| &run.args).filter(|arg| is_options_struct(arg));
+    if let Some(first) = structs.next() {
+        if let Some(option) = command.options.first() {
+            return Err(
+                Error::new(
+                    option.span,
+                    format_args!("`{}` already takes its options from an `#[options]` struct, declare this one there", func.name)
+                )
+            );
+        }
+        if let Some(second) = structs.next() {
+            return Err(
+                Error::new(
+                    second.name.span(),
+                    format_args!("`{}` already takes its options from `{}`, declare these there", func.name, first.name)
+                )
+            );
+        }
+    }
+
+    let count = command.options.len() + func.subcommands.len();
+    if count > MAX_OPTIONS {
+        return Err(
+            Error::new(
+                span,
+                format_args!("this command has {count} options and subcommands, at most {MAX_OPTIONS} are allowed")
+            )
+        );
+    }
+
+    options(&command.options)
+}
+
+/// Checks a module of a `#[user_command]` or `#[message_command]`, which Discord only gives a
+/// name: no description, options or subcommands.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid or the module declares anything else Discord has
+/// no place for.
+pub fn context_menu(func: &CommandFun, kind: MenuKind, menu: &LitStr, command: &CommandAttrs) -> Result<()> {
+    menu_name(&menu.value(), menu.span())?;
+
+    let span = func.name.span();
+    let attribute = kind.attribute();
+    if command.description.is_some() {
+        return Err(Error::new(span, format_args!("a `#[{attribute}]` can't have a description")));
+    }
+    if let Some(option) = command.options.first() {
+        return Err(Error::new(option.span, format_args!("a `#[{attribute}]` can't have options")));
+    }
+    if let Some(subcommand) = func.subcommands.first() {
+        return Err(
+            Error::new(subcommand.name.span(), format_args!("a `#[{attribute}]` can't have subcommands"))
+        );
+    }
+    if let Some(hook) = func.autocompletes.first() {
+        return Err(
+            Error::new(
+                hook.func.sig.ident.span(),
+                format_args!("a `#[{attribute}]` has no options to autocomplete")
+            )
+        );
+    }
+    if command.catalog.is_some() || !command.localizations.descriptions.is_empty() {
+        return Err(
+            Error::new(
+                span,
+                format_args!("a `#[{attribute}]` only takes translated names, through `#[name_localized(..)]`")
+            )
+        );
+    }
+
+    for (locale, localized) in &command.localizations.names {
+        menu_name(localized, span)
+            .map_err(|why| Error::new(span, format_args!("the `{locale}` name: {why}")))?;
+    }
+
+    Ok(())
+}
+
+/// Checks that a context-menu command name is 1 to 32 characters long. Unlike slash commands,
+/// these can have capitals and spaces.
+///
+/// # Errors
+///
+/// Returns an error at `span` if it isn't.
+pub fn menu_name(name: &str, span: Span) -> Result<()> {
+    let length = name.chars().count();
+    if (1..=MAX_NAME_LENGTH).contains(&length) {
+        Ok(())
+    } else {
+        Err(
+            Error::new(
+                span,
+                format_args!("`{name}` is {length} characters long, names have to be 1 to {MAX_NAME_LENGTH}")
+            )
+        )
+    }
+}
+
+/// Checks that a command, subcommand or option name is 1 to 32 lowercase letters, numbers,
+/// `-` or `_`.
+///
+/// # Errors
+///
+/// Returns an error at `span` if it isn't.
+pub fn name(name: &str, span: Span) -> Result<()> {
+    let length = name.chars().count();
+    if !(1..=MAX_NAME_LENGTH).contains(&length) {
+        return Err(
+            Error::new(
+                span,
+                format_args!("`{name}` is {length} characters long, names have to be 1 to {MAX_NAME_LENGTH}")
+            )
+        );
+    }
+
+    if let Some(invalid) = name.chars().find(|&c| !(c == '-' || c == '_' || c.is_alphanumeric())) {
+        return Err(
+            Error::new(
+                span,
+                format_args!("`{name}` can't contain `{invalid}`, names can only have letters, numbers, `-` and `_`")
+            )
+        );
+    }
+
+    if name.to_lowercase() != name {
+        return Err(Error::new(span, format_args!("`{name}` has to be lowercase")));
+    }
+
+    Ok(())
+}
+
+/// Checks that a command, subcommand or option description is 1 to 100 characters long.
+///
+/// # Errors
+///
+/// Returns an error at `span` if it isn't.
+pub fn description(description: &str, span: Span) -> Result<()> {
+    let length = description.chars().count();
+    if (1..=MAX_DESCRIPTION_LENGTH).contains(&length) {
+        Ok(())
+    } else {
+        Err(
+            Error::new(
+                span,
+                format_args!("this description is {length} characters long, descriptions have to be 1 to {MAX_DESCRIPTION_LENGTH}")
+            )
+        )
+    }
+}
+
+/// Checks translated names and descriptions by the same rules as the default ones.
+///
+/// # Errors
+///
+/// Returns an error at `span` for the first one that breaks them.
+pub fn localizations(localizations: &Localizations, span: Span) -> Result<()> {
+    for (locale, localized) in &localizations.names {
+        name(localized, span)
+            .map_err(|why| Error::new(span, format_args!("the `{locale}` name: {why}")))?;
+    }
+    for (locale, localized) in &localizations.descriptions {
+        description(localized, span)
+            .map_err(|why| Error::new(span, format_args!("the `{locale}` description: {why}")))?;
+    }
+
+    Ok(())
+}
+
+/// Checks that there are at most 25 options and that every required option comes before the
+/// optional ones, as Discord won't accept them in any other order.
+///
+/// # Errors
+///
+/// Returns an error at the first option out of place.
+pub fn options(options: &[CommandOption]) -> Result<()> {
+    if let Some(option) = options.get(MAX_OPTIONS) {
+        return Err(
+            Error::new(option.span, format_args!("at most {MAX_OPTIONS} options are allowed"))
+        );
+    }
+
+    let mut optional = None;
+    for option in options {
+        localizations(&option.localizations, option.span)?;
+        option_order(&option.name, option.required, &mut optional, option.span)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that a required option doesn't come after an optional one, given the first optional
+/// option seen so far, which is updated when `name` is the first.
+///
+/// # Errors
+///
+/// Returns an error at `span` if `name` is required and comes after `optional`.
+pub fn option_order(name: &str, required: bool, optional: &mut Option<String>, span: Span) -> Result<()> {
+    match (required, &optional) {
+        (false, None) => *optional = Some(name.to_owned()),
+        (true, Some(optional)) => {
+            return Err(
+                Error::new(
+                    span,
+                    format_args!("required option `{name}` has to come before optional option `{optional}`")
+                )
+            );
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Checks that a `#[component("..")]` or `#[modal("..")]` prefix is 1 to 32 characters long and
+/// has no `:`, which separates it from the rest of the `custom_id`.
+///
+/// # Errors
+///
+/// Returns an error at `span` if it isn't.
+pub fn prefix(prefix: &str, span: Span) -> Result<()> {
+    let length = prefix.chars().count();
+    if !(1..=MAX_PREFIX_LENGTH).contains(&length) {
+        return Err(
+            Error::new(
+                span,
+                format_args!("`{prefix}` is {length} characters long, prefixes have to be 1 to {MAX_PREFIX_LENGTH}")
+            )
+        );
+    }
+
+    if prefix.contains(':') {
+        return Err(Error::new(span, format_args!("`{prefix}` can't contain `:`")));
+    }
+
+    Ok(())
+}
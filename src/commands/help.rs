@@ -0,0 +1,53 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Lists the bot's commands, or explains one of them")]
+#[category("Utility")]
+#[option(string, "command", "The command to explain")]
+#[option(integer, "page", "The page of the command list to show")]
+mod help {
+    use serenity::all::{ AutocompleteChoice, CommandInteraction };
+    use serenity::prelude::Context;
+    use crate::interactions::error::CommandError;
+    use crate::interactions::help;
+    use crate::interactions::response::CommandResponse;
+
+    pub fn run(command: Option<String>, page: Option<i64>) -> Result<CommandResponse, CommandError> {
+        if let Some(name) = command {
+            return help::find(&name)
+                .map(|command| CommandResponse::from(help::details(&command)))
+                .ok_or_else(|| CommandError::user(format!("There's no command called `{name}`")));
+        }
+
+        let mut pages = help::pages();
+        let Some(last) = pages.len().checked_sub(1) else {
+            return Err(CommandError::internal("No commands are registered"));
+        };
+
+        // Pages are numbered from 1 for users, anything out of range shows the closest page
+        let index = page
+            .and_then(|page| usize::try_from(page.saturating_sub(1)).ok())
+            .unwrap_or_default()
+            .min(last);
+
+        Ok(CommandResponse::from(pages.swap_remove(index)))
+    }
+
+    /// Suggests the commands whose name contains what has been typed so far.
+    #[autocomplete(option = "command")]
+    pub fn suggest_command(
+        _ctx: &Context,
+        _command: &CommandInteraction,
+        value: &str
+    ) -> Vec<AutocompleteChoice> {
+        let value = value.to_lowercase();
+
+        help::commands()
+            .into_iter()
+            .filter(|command| command.name.contains(&value))
+            .map(|command| {
+                AutocompleteChoice::new(format!("{}{}", command.prefix, command.name), command.name)
+            })
+            .collect()
+    }
+}
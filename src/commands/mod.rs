@@ -1,2 +1,4 @@
+pub mod help;
+pub mod moderation;
 pub mod owner;
 pub mod user;
@@ -0,0 +1,104 @@
+use std::env;
+
+use greys_macros::message_command;
+use serenity::all::{ ChannelId, Message };
+use serenity::builder::{ CreateEmbed, CreateEmbedAuthor };
+
+use crate::interactions::error::CommandError;
+
+/// Reads the channel messages are sent to from `key`, set in the environment.
+fn channel_from_env(key: &str) -> Result<ChannelId, CommandError> {
+    env::var(key)
+        .ok()
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| {
+            CommandError::user(format!("No channel is set up for this, ask the bot's owner to set `{key}`"))
+        })
+}
+
+/// Renders a message as an embed, with its author, content and a link back to it.
+fn message_embed(message: &Message) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .author(CreateEmbedAuthor::new(message.author.tag()).icon_url(message.author.face()))
+        .field("Source", message.link(), false)
+        .timestamp(message.timestamp);
+
+    if !message.content.is_empty() {
+        embed = embed.description(&message.content);
+    }
+    if let Some(image) = message.attachments.iter().find(|attachment| attachment.height.is_some()) {
+        embed = embed.image(&image.url);
+    }
+
+    embed
+}
+
+#[message_command("Pin or unpin")]
+#[category("Moderation")]
+#[guild_only]
+#[default_member_permissions(MANAGE_MESSAGES)]
+mod pin_message {
+    use serenity::all::Message;
+    use serenity::prelude::Context;
+    use crate::interactions::error::CommandError;
+
+    pub async fn run(ctx: &Context, message: &Message) -> Result<String, CommandError> {
+        if message.pinned {
+            message.unpin(&ctx.http).await?;
+            return Ok("Unpinned the message".to_owned());
+        }
+
+        message.pin(&ctx.http).await?;
+
+        Ok("Pinned the message".to_owned())
+    }
+}
+
+#[message_command("Quote to channel")]
+#[category("Moderation")]
+#[guild_only]
+#[default_member_permissions(MANAGE_MESSAGES)]
+#[cooldown(channel, 5)]
+mod quote_message {
+    use serenity::all::Message;
+    use serenity::builder::CreateMessage;
+    use serenity::prelude::Context;
+    use crate::interactions::error::CommandError;
+    use super::{ channel_from_env, message_embed };
+
+    pub async fn run(ctx: &Context, message: &Message) -> Result<String, CommandError> {
+        let channel = channel_from_env("QUOTE_CHANNEL_ID")?;
+
+        channel.send_message(&ctx.http, CreateMessage::new().embed(message_embed(message))).await?;
+
+        Ok(format!("Quoted the message in <#{channel}>"))
+    }
+}
+
+#[message_command("Report message")]
+#[category("Moderation")]
+#[guild_only]
+#[cooldown(user, 60)]
+mod report_message {
+    use serenity::all::{ CommandInteraction, Message };
+    use serenity::builder::CreateMessage;
+    use serenity::prelude::Context;
+    use crate::interactions::error::CommandError;
+    use super::{ channel_from_env, message_embed };
+
+    pub async fn run(
+        ctx: &Context,
+        command: &CommandInteraction,
+        message: &Message
+    ) -> Result<String, CommandError> {
+        let channel = channel_from_env("REPORT_CHANNEL_ID")?;
+
+        let report = message_embed(message)
+            .title("Reported message")
+            .field("Reported by", format!("<@{}>", command.user.id), true)
+            .field("Channel", format!("<#{}>", message.channel_id), true);
+        channel.send_message(&ctx.http, CreateMessage::new().embed(report)).await?;
+
+        Ok("Thanks, the moderators have been told about the message".to_owned())
+    }
+}
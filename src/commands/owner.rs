@@ -1,3 +1,4 @@
+use greys_macros::cooldown;
 use serenity::{
     framework::standard::{ macros::command, Args, CommandResult },
     client::Context,
@@ -54,7 +55,10 @@ use crate::ShardManagerContainer;
 ///     Ok(())
 /// }
 /// ```
+#[cooldown(channel, 10)]
 #[command]
+#[description = "Shows or sets the slow mode rate of the channel"]
+#[usage = "[seconds]"]
 pub async fn slow_mode(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let say_content = if let Ok(slow_mode_rate_seconds) = args.single::<u16>() {
         let builder = EditChannel::new().rate_limit_per_user(slow_mode_rate_seconds);
@@ -77,31 +81,31 @@ pub async fn slow_mode(ctx: &Context, msg: &Message, mut args: Args) -> CommandR
     Ok(())
 }
 
+#[cooldown(user, 5)]
 #[command]
+#[description = "Shows the latency of the shard this guild is on"]
 pub async fn latency(ctx: &Context, msg: &Message) -> CommandResult {
-    let data = ctx.data.read().await;
+    let latency = {
+        let data = ctx.data.read().await;
 
-    let shard_manager = match data.get::<ShardManagerContainer>() {
-        Some(v) => v,
-        None => {
+        let Some(shard_manager) = data.get::<ShardManagerContainer>() else {
+            drop(data);
             msg.reply(ctx, "There was a problem getting the shard manager").await?;
 
             return Ok(());
-        }
-    };
+        };
 
-    let runners = shard_manager.runners.lock().await;
+        let runners = shard_manager.runners.lock().await;
+        runners.get(&ctx.shard_id).map(|runner| runner.latency)
+    };
 
-    let runner = match runners.get(&ctx.shard_id) {
-        Some(runner) => runner,
-        None => {
-            msg.reply(ctx, "No shard found").await?;
+    let Some(latency) = latency else {
+        msg.reply(ctx, "No shard found").await?;
 
-            return Ok(());
-        }
+        return Ok(());
     };
 
-    msg.reply(ctx, &format!("The shard latency is {:?}", runner.latency)).await?;
+    msg.reply(ctx, &format!("The shard latency is {latency:?}")).await?;
 
     Ok(())
 }
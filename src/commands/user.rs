@@ -1,48 +1,162 @@
-use greys_macros::slash_command;
+use greys_macros::{ slash_command, user_command, SlashOptions };
+use serenity::builder::CreateAttachment;
 
-pub mod id {
-    use serenity::all::GuildId;
-    use serenity::builder::{ CreateCommand, CreateCommandOption };
-    use serenity::model::application::{ CommandOptionType, ResolvedOption, ResolvedValue };
+use crate::interactions::response::CommandResponse;
+use crate::voice_handler::sink::Format;
+
+/// The most characters Discord accepts in a single message.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// Responds with a list of ids, or with `summary` and the list as a file when it's too long to
+/// fit in a message.
+fn id_list(ids: String, summary: impl FnOnce() -> String) -> CommandResponse {
+    if ids.len() > MAX_MESSAGE_LENGTH {
+        return CommandResponse::new()
+            .content(summary())
+            .attachment(CreateAttachment::bytes(ids, "ids.txt"));
+    }
+
+    CommandResponse::from(ids)
+}
+
+#[derive(SlashOptions)]
+pub struct IdOptions {
+    #[description("The role to lookup")]
+    #[description_localized("de", "Die Rolle, die nachgeschlagen werden soll")]
+    #[autocomplete]
+    role: String,
+}
+
+#[slash_command]
+#[description("Get user ids")]
+#[description_localized("de", "Zeigt die IDs der Mitglieder einer Rolle")]
+#[category("Members")]
+#[guild_only]
+#[defer]
+#[cooldown(user, 30)]
+#[cooldown(guild, 10)]
+mod id {
+    use std::collections::HashMap;
+    use std::fmt::Write;
+
+    use serenity::all::{ AutocompleteChoice, CommandInteraction, GuildId };
     use serenity::model::id::RoleId;
     use serenity::prelude::Context;
+    use serenity::utils::parse_role_mention;
+    use crate::interactions::error::CommandError;
+    use crate::interactions::response::CommandResponse;
+    use super::{ id_list, IdOptions };
 
-    pub fn register() -> CreateCommand {
-        CreateCommand::new("id")
-            .description("Get user ids")
-            .add_option(
-                CreateCommandOption::new(CommandOptionType::Role, "role", "The role to lookup")
-                    .required(true)
-            )
-    }
+    pub async fn run(
+        ctx: &Context,
+        guild_id: GuildId,
+        #[options] args: IdOptions
+    ) -> Result<CommandResponse, CommandError> {
+        let Some(role) = parse_role_mention(&args.role).or_else(|| args.role.parse::<RoleId>().ok()) else {
+            return Err(CommandError::user("Please provide a valid role"));
+        };
+
+        let members = guild_id.members(&ctx.http, None, None).await?;
 
-    pub async fn run(ctx: &Context, guild_id: GuildId, options: &[ResolvedOption<'_>]) -> String {
         let mut response = String::new();
+        let mut count = 0;
+        for member in members.iter().filter(|member| member.roles.contains(&role)) {
+            let _ = writeln!(response, "{}'s id is {}", member.user.tag(), member.user.id);
+            count += 1;
+        }
 
-        for option in options {
-            if let ResolvedOption { value: ResolvedValue::Role(role), .. } = option {
-                let guild_id = guild_id;
-                let members = guild_id.members(&ctx.http, None, None).await.unwrap();
+        if response.is_empty() {
+            return Err(CommandError::user("Please provide a valid role"));
+        }
 
-                let role_id = RoleId::new(role.id.get());
-                for member in members {
-                    if member.roles.contains(&role_id) {
-                        response.push_str(&format!("{}'s id is {}\n", member.user.tag(), member.user.id));
-                    }
-                }
-            }
+        // Large roles don't fit in a message, so the list is sent as a file instead
+        Ok(id_list(response, || format!("{count} members have <@&{role}>")))
+    }
+
+    /// Suggests the guild's roles matching what has been typed so far, with how many of the
+    /// cached members have them. Only reads the cache, as it runs on every keystroke.
+    #[autocomplete(option = "role")]
+    pub fn suggest_role(
+        ctx: &Context,
+        command: &CommandInteraction,
+        value: &str
+    ) -> Vec<AutocompleteChoice> {
+        let Some(guild) = command.guild_id.and_then(|guild_id| ctx.cache.guild(guild_id)) else {
+            return Vec::new();
+        };
+
+        let mut member_counts: HashMap<RoleId, usize> = HashMap::new();
+        for role in guild.members.values().flat_map(|member| &member.roles) {
+            *member_counts.entry(*role).or_default() += 1;
         }
 
-        if response.is_empty() {
-            return "Please provide a valid role".to_string();
+        let value = value.to_lowercase();
+        let mut roles: Vec<_> = guild.roles
+            .values()
+            // The @everyone role, which everyone has
+            .filter(|role| role.id.get() != guild.id.get())
+            .filter(|role| role.name.to_lowercase().contains(&value))
+            .collect();
+        roles.sort_unstable_by_key(|role| std::cmp::Reverse(role.position));
+
+        roles
+            .into_iter()
+            .map(|role| {
+                let count = member_counts.get(&role.id).copied().unwrap_or_default();
+                AutocompleteChoice::new(format!("{} ({count} members)", role.name), role.id.to_string())
+            })
+            .collect()
+    }
+}
+
+#[user_command("Get IDs")]
+#[category("Members")]
+#[guild_only]
+#[cooldown(user, 10)]
+mod user_ids {
+    use std::cmp::Reverse;
+    use std::fmt::Write;
+
+    use serenity::all::{ GuildId, PartialMember, User };
+    use serenity::prelude::Context;
+    use crate::interactions::response::CommandResponse;
+    use super::id_list;
+
+    pub fn run(
+        ctx: &Context,
+        guild_id: GuildId,
+        user: &User,
+        member: Option<&PartialMember>
+    ) -> CommandResponse {
+        let mut response = String::new();
+        let _ = writeln!(response, "{}'s id is {}", user.tag(), user.id);
+
+        let role_ids = member.map(|member| member.roles.as_slice()).unwrap_or_default();
+        let mut roles: Vec<_> = ctx.cache
+            .guild(guild_id)
+            .map(|guild| {
+                role_ids
+                    .iter()
+                    .filter_map(|id| guild.roles.get(id))
+                    .map(|role| (role.position, role.id, role.name.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        roles.sort_unstable_by_key(|(position, _, _)| Reverse(*position));
+
+        for (_, id, name) in &roles {
+            let _ = writeln!(response, "{name}'s id is {id}");
         }
-    
-        response
+
+        id_list(response, || format!("{} has {} roles", user.tag(), roles.len()))
     }
 }
+
 // #[description("It Pings")]
 #[slash_command]
 #[description("It Pings")]
+#[category("Utility")]
+#[localizations("locales/commands.toml")]
 mod ping {
     use serenity::all::ResolvedOption;
 
@@ -53,127 +167,451 @@ mod ping {
 
 #[slash_command]
 #[description("Creates a meeting room for and notifies the requested users of said room")]
+#[category("Members")]
+#[guild_only]
+#[default_member_permissions(MANAGE_CHANNELS)]
+#[defer(on_budget, public)]
 mod create_meeting {
-    use serenity::all::{ ResolvedValue, ResolvedOption };
-    use serenity::all::CommandOptionType;
-
-    pub fn run(options: &[ResolvedOption]) -> String {
-        for option in options {
-            if
-                let ResolvedOption {
-                    value: ResolvedValue::Autocomplete {
-                        kind: CommandOptionType::SubCommand,
-                        value: "Users",
-                    },
-                    name: "id",
-                    ..
-                } = option
-            {
+    #[subcommand]
+    #[description("Creates a meeting with a single user")]
+    #[option(user, "user", "The user to meet with", required)]
+    mod user {
+        use std::time::Duration;
+
+        use greys_macros::{ component, modal };
+        use serenity::all::{
+            ButtonStyle,
+            CommandInteraction,
+            ComponentInteraction,
+            InputTextStyle,
+            ModalInteraction,
+        };
+        use serenity::builder::{ CreateActionRow, CreateButton, CreateEmbed, CreateInputText, CreateModal };
+        use serenity::model::id::UserId;
+        use serenity::prelude::Context;
+        use crate::interactions::components::{ modal_value, CustomId };
+        use crate::interactions::error::CommandError;
+        use crate::interactions::response::{ CommandResponse, ComponentResponse };
+
+        /// How long the invited user has to answer.
+        const INVITE_EXPIRY: Duration = Duration::from_hours(24);
+
+        pub fn run(command: &CommandInteraction, user: UserId) -> Result<CommandResponse, CommandError> {
+            invite(command.user.id, user)
+        }
+
+        /// The invite message, with buttons for the guest to answer and the host to add details.
+        fn invite(host: UserId, guest: UserId) -> Result<CommandResponse, CommandError> {
+            let reply = |accepted: bool| {
+                CustomId::new("meeting_reply")
+                    .with_payload(&(host, guest, accepted))
+                    .map(|id| id.expires_in(INVITE_EXPIRY))
+                    .and_then(|id| id.encode())
+            };
+            let details = CustomId::new("meeting_details")
+                .with_payload(&(host, guest))?
+                .expires_in(INVITE_EXPIRY)
+                .encode()?;
+
+            let buttons = CreateActionRow::Buttons(vec![
+                CreateButton::new(reply(true)?).label("Accept").style(ButtonStyle::Success),
+                CreateButton::new(reply(false)?).label("Decline").style(ButtonStyle::Danger),
+                CreateButton::new(details).label("Add details").style(ButtonStyle::Secondary)
+            ]);
+
+            Ok(
+                CommandResponse::from(format!("<@{host}> invited <@{guest}> to a meeting"))
+                    .action_row(buttons)
+                    .ephemeral(false)
+            )
+        }
+
+        /// Accepts or declines the invite, which only the invited user can do.
+        #[component("meeting_reply")]
+        pub fn reply(
+            _ctx: &Context,
+            interaction: &ComponentInteraction,
+            id: &CustomId
+        ) -> Result<ComponentResponse, CommandError> {
+            let (host, guest, accepted): (UserId, UserId, bool) = id.payload()?;
+            if interaction.user.id != guest {
+                return Err(CommandError::user("This invite isn't for you"));
+            }
+
+            let answer = if accepted { "accepted" } else { "declined" };
+            let response = interaction.message.embeds
+                .iter()
+                .cloned()
+                .map(CreateEmbed::from)
+                .fold(
+                    CommandResponse::from(format!("<@{guest}> {answer} <@{host}>'s meeting")),
+                    CommandResponse::embed
+                );
+
+            Ok(ComponentResponse::Update(response))
+        }
+
+        /// Opens the modal for the host to fill in the meeting's details.
+        #[component("meeting_details")]
+        pub fn details(
+            _ctx: &Context,
+            interaction: &ComponentInteraction,
+            id: &CustomId
+        ) -> Result<ComponentResponse, CommandError> {
+            let (host, _guest): (UserId, UserId) = id.payload()?;
+            if interaction.user.id != host {
+                return Err(CommandError::user("Only the host can add details to the meeting"));
             }
+
+            let modal = CreateModal::new(id.encode()?, "Meeting details").components(vec![
+                CreateActionRow::InputText(
+                    CreateInputText::new(InputTextStyle::Short, "When", "when")
+                        .placeholder("Tomorrow at 3pm")
+                        .max_length(100)
+                ),
+                CreateActionRow::InputText(
+                    CreateInputText::new(InputTextStyle::Paragraph, "Agenda", "agenda")
+                        .max_length(1000)
+                        .required(false)
+                )
+            ]);
+
+            Ok(ComponentResponse::Modal(modal))
         }
 
-        String::from("Ran create_meeting successfully")
+        /// Adds the submitted details to the invite.
+        #[modal("meeting_details")]
+        pub fn submit_details(
+            _ctx: &Context,
+            interaction: &ModalInteraction,
+            id: &CustomId
+        ) -> Result<ComponentResponse, CommandError> {
+            let (host, guest): (UserId, UserId) = id.payload()?;
+
+            let mut embed = CreateEmbed::new()
+                .title("Meeting details")
+                .field("When", modal_value(interaction, "when").unwrap_or("Not set"), false);
+            if let Some(agenda) = modal_value(interaction, "agenda") {
+                embed = embed.field("Agenda", agenda, false);
+            }
+
+            Ok(ComponentResponse::Update(invite(host, guest)?.embed(embed)))
+        }
+    }
+
+    #[subcommand]
+    #[description("Creates a meeting with everyone in a role")]
+    #[option(role, "role", "The role to meet with", required)]
+    mod role {
+        use serenity::model::id::RoleId;
+        use crate::interactions::response::CommandResponse;
+
+        pub fn run(role: RoleId) -> CommandResponse {
+            CommandResponse::from(format!("Ran create_meeting successfully for <@&{role}>"))
+                .ephemeral(false)
+        }
     }
 }
 
-pub mod join_channel {
-    use serenity::all::{ Channel, ChannelType, CommandOptionType, ResolvedOption, ResolvedValue };
+#[slash_command]
+#[description("Simply makes the bot join a channel")]
+#[category("Voice")]
+#[localizations("locales/commands.toml")]
+#[guild_only]
+#[default_member_permissions(MOVE_MEMBERS)]
+#[defer(public)]
+#[option(string, "channel", "The voice channel to join", required)]
+mod join_channel {
+    use serenity::all::{ AutocompleteChoice, Channel, ChannelType, CommandInteraction, Permissions };
     use serenity::all::GuildId;
-    use serenity::builder::{ CreateCommand, CreateCommandOption };
     use serenity::prelude::Context;
     use serenity::model::id::ChannelId;
+    use serenity::utils::parse_channel_mention;
+    use crate::interactions::error::CommandError;
     use crate::voice_handler::voice_channel::join_voice_channel;
 
-    pub fn register() -> CreateCommand {
-        CreateCommand::new("join_channel")
-            .description("Simply makes the bot join a channel")
-            .add_option(
-                CreateCommandOption::new(
-                    CommandOptionType::Channel,
-                    "channel",
-                    "The channel to join"
-                ).required(true)
-            )
-    }
+    pub async fn run(ctx: &Context, guild_id: GuildId, channel: String) -> Result<String, CommandError> {
+        let Some(channel) = parse_channel_mention(&channel).or_else(|| channel.parse::<ChannelId>().ok()) else {
+            return Err(CommandError::user("Please provide a valid channel"));
+        };
 
-    pub async fn run(ctx: &Context, guild_id: GuildId, options: &[ResolvedOption<'_>]) -> String {
-        let channel_id = options.iter().find_map(|option| {
-            if let ResolvedOption { value: ResolvedValue::Channel(channel), .. } = option {
-                Some(ChannelId::new(channel.id.get()))
-            } else {
-                None
-            }
-        });
-
-        match channel_id {
-            Some(channel_id) => {
-                // Fetch the channel to check if it's a voice channel
-                match channel_id.to_channel(ctx).await {
-                    Ok(Channel::Guild(channel)) if channel.kind == ChannelType::Voice => {
-                        match join_voice_channel(ctx, guild_id, channel_id).await {
-                            Ok(_) => "Successfully joined voice channel".to_string(),
-                            Err(e) => format!("Failed to join voice channel: {}", e),
-                        }
-                    }
-                    _ => "Please provide a valid voice channel".to_string(),
-                }
-            }
-            None => "Please provide a valid channel".to_string(),
+        // Fetch the channel to check if it's a voice channel
+        let guild_channel = match channel.to_channel(ctx).await {
+            Ok(Channel::Guild(guild_channel)) if guild_channel.kind == ChannelType::Voice => guild_channel,
+            _ => return Err(CommandError::user("Please provide a valid voice channel")),
+        };
+
+        let bot_id = ctx.cache.current_user().id;
+        if !guild_channel.permissions_for_user(ctx, bot_id)?.contains(Permissions::CONNECT) {
+            return Err(CommandError::permission("I'm not allowed to connect to that channel"));
         }
+
+        join_voice_channel(ctx, guild_id, channel).await?;
+
+        Ok("Successfully joined voice channel".to_string())
+    }
+
+    /// Suggests the voice channels matching what has been typed so far that the bot is
+    /// allowed to connect to.
+    #[autocomplete(option = "channel")]
+    pub fn suggest_channel(
+        ctx: &Context,
+        command: &CommandInteraction,
+        value: &str
+    ) -> Vec<AutocompleteChoice> {
+        let bot_id = ctx.cache.current_user().id;
+        let Some(guild) = command.guild_id.and_then(|guild_id| ctx.cache.guild(guild_id)) else {
+            return Vec::new();
+        };
+        let Some(bot) = guild.members.get(&bot_id) else {
+            return Vec::new();
+        };
+
+        let value = value.to_lowercase();
+        let mut channels: Vec<_> = guild.channels
+            .values()
+            .filter(|channel| channel.kind == ChannelType::Voice)
+            .filter(|channel| channel.name.to_lowercase().contains(&value))
+            .filter(|channel| guild.user_permissions_in(channel, bot).contains(Permissions::CONNECT))
+            .collect();
+        channels.sort_unstable_by_key(|channel| channel.position);
+
+        channels
+            .into_iter()
+            .map(|channel| AutocompleteChoice::new(channel.name.clone(), channel.id.to_string()))
+            .collect()
     }
 }
 
 #[slash_command]
 #[description("Simply makes the bot leave a channel")]
+#[category("Voice")]
+#[localizations("locales/commands.toml")]
+#[guild_only]
+#[default_member_permissions(MOVE_MEMBERS)]
 mod leave_channel {
     use serenity::all::ResolvedOption;
     use serenity::all::GuildId;
     use serenity::prelude::Context;
+    use crate::interactions::error::CommandError;
+    use crate::voice_handler::recording;
     use crate::voice_handler::voice_channel::leave_voice_channel;
 
-    pub async fn run(ctx: &Context, guild_id: GuildId, _options: &[ResolvedOption<'_>]) -> String {
-        if let Err(e) = leave_voice_channel(ctx, guild_id).await {
-            format!("Failed to leave voice channel: {}", e)
-        } else {
-            "Successfully left voice channel".to_string()
+    pub async fn run(
+        ctx: &Context,
+        guild_id: GuildId,
+        _options: &[ResolvedOption<'_>]
+    ) -> Result<String, CommandError> {
+        // The recording is gone either way, so the bot leaves before a failed save is reported
+        let recorded = recording::stop(ctx, guild_id).await;
+        if let Err(why) = leave_voice_channel(ctx, guild_id).await {
+            if let Err(saving) = &recorded {
+                println!("Cannot save the recording of guild {guild_id}: {saving}");
+            }
+            return Err(why.into());
         }
+        let recorded = recorded?;
+
+        Ok(
+            recorded.map_or_else(
+                || "Successfully left voice channel".to_string(),
+                |(session, paths)| {
+                    let mut message = format!("Successfully left voice channel, the recording was saved to {} file(s)", paths.len());
+                    if let Some(dropped) = session.describe_dropped() {
+                        message.push('\n');
+                        message.push_str(&dropped);
+                    }
+                    message
+                }
+            )
+        )
     }
 }
 
-pub mod record_voice {
-    use serenity::{ all::CommandOptionType, builder::{ CreateCommand, CreateCommandOption } };
-
-    pub fn _register() -> CreateCommand {
-        CreateCommand::new("record_voice")
-            .description("Makes the bot record voice data until commanded to stop")
-            .add_option(
-                CreateCommandOption::new(
-                    CommandOptionType::Boolean,
-                    "record",
-                    "Whether the bot should be recording"
-                ).required(true)
+#[derive(SlashOptions)]
+pub struct RecordOptions {
+    #[description("Whether to also save everyone mixed into one file, yes unless set")]
+    mixdown: Option<bool>,
+
+    #[description("What to save the recording as, the server's format unless set")]
+    format: Option<Format>,
+}
+
+#[derive(SlashOptions)]
+pub struct FormatOptions {
+    #[description("What to save recordings as from now on, shows the current format unless set")]
+    format: Option<Format>,
+}
+
+#[slash_command]
+#[description("Records the voice channel the bot is in")]
+#[category("Voice")]
+#[guild_only]
+#[default_member_permissions(MOVE_MEMBERS)]
+#[defer(on_budget, public)]
+mod record_voice {
+    #[subcommand]
+    #[description("Starts recording everyone in the voice channel")]
+    mod start {
+        use serenity::all::{ CommandInteraction, GuildId };
+        use serenity::prelude::Context;
+        use crate::interactions::error::CommandError;
+        use crate::interactions::response::CommandResponse;
+        use crate::voice_handler::recording;
+        use super::super::RecordOptions;
+
+        pub async fn run(
+            ctx: &Context,
+            command: &CommandInteraction,
+            guild_id: GuildId,
+            #[options] args: RecordOptions
+        ) -> Result<CommandResponse, CommandError> {
+            let mixdown = args.mixdown.unwrap_or(true);
+            let session = recording::start(ctx, guild_id, command.user.id, mixdown, args.format).await?;
+
+            // Public, so everyone in the channel knows they're being recorded
+            let response = format!("Recording the voice channel as {}, {}", session.format, session.describe());
+            Ok(CommandResponse::from(response).ephemeral(false))
+        }
+    }
+
+    #[subcommand]
+    #[description("Stops the recording and saves it, staying in the voice channel")]
+    mod stop {
+        use std::fs;
+
+        use serenity::all::GuildId;
+        use serenity::builder::CreateAttachment;
+        use serenity::prelude::Context;
+        use crate::interactions::error::CommandError;
+        use crate::interactions::response::CommandResponse;
+        use crate::voice_handler::recording;
+
+        /// The most Discord lets a bot upload to a guild without boosts in one message.
+        const MAX_ATTACHMENT_SIZE: u64 = 10 * 1024 * 1024;
+
+        /// The most files Discord accepts on one message.
+        const MAX_ATTACHMENTS: usize = 10;
+
+        pub async fn run(ctx: &Context, guild_id: GuildId) -> Result<CommandResponse, CommandError> {
+            let Some((session, paths)) = recording::stop(ctx, guild_id).await? else {
+                return Err(CommandError::user("Nothing is being recorded"));
+            };
+
+            let files: Vec<String> = paths.iter().map(|path| format!("`{}`", path.display())).collect();
+            let mut summary = format!("Stopped the recording {}, saved as {}", session.describe(), files.join(", "));
+            if let Some(dropped) = session.describe_dropped() {
+                summary.push('\n');
+                summary.push_str(&dropped);
+            }
+
+            // Attaches every file that fits in a message, spread over follow-ups once one is full.
+            // The rest are listed, and can still be found on the bot's host
+            let mut batches = vec![Vec::new()];
+            let mut size = 0;
+            let mut unattached = Vec::new();
+            for path in &paths {
+                let file = match fs::metadata(path) {
+                    Ok(file) if file.len() > MAX_ATTACHMENT_SIZE => {
+                        unattached.push(format!("`{}` is too big to attach", path.display()));
+                        continue;
+                    }
+                    Ok(file) => file,
+                    Err(why) => {
+                        unattached.push(format!("`{}` can't be read: {why}", path.display()));
+                        continue;
+                    }
+                };
+
+                let attachment = match CreateAttachment::path(path).await {
+                    Ok(attachment) => attachment,
+                    Err(why) => {
+                        unattached.push(format!("`{}` can't be attached: {why}", path.display()));
+                        continue;
+                    }
+                };
+
+                let batch = batches.last().map_or(0, Vec::len);
+                if batch == MAX_ATTACHMENTS || size + file.len() > MAX_ATTACHMENT_SIZE {
+                    batches.push(Vec::new());
+                    size = 0;
+                }
+                batches.last_mut().expect("there's always a batch").push(attachment);
+                size += file.len();
+            }
+            for part in unattached {
+                summary.push_str("\n- ");
+                summary.push_str(&part);
+            }
+
+            // The summary goes on its own, so it's sent even if uploading a part fails
+            let first = CommandResponse::from(summary).ephemeral(false);
+            Ok(
+                batches
+                    .into_iter()
+                    .filter(|batch| !batch.is_empty())
+                    .map(|batch| {
+                        batch.into_iter().fold(CommandResponse::new().ephemeral(false), CommandResponse::attachment)
+                    })
+                    .fold(first, CommandResponse::follow_up)
             )
+        }
     }
 
-    pub fn _run() -> String {
-        "in development".to_owned()
+    #[subcommand]
+    #[description("Shows whether the voice channel is being recorded")]
+    mod status {
+        use serenity::all::GuildId;
+        use serenity::prelude::Context;
+        use crate::voice_handler::recording;
+
+        pub async fn run(ctx: &Context, guild_id: GuildId) -> String {
+            recording::session(ctx, guild_id).await.map_or_else(
+                || "Nothing is being recorded".to_owned(),
+                |session| format!("Recording as {}, {}", session.format, session.describe())
+            )
+        }
+    }
+
+    #[subcommand]
+    #[description("Sets what this server's recordings are saved as")]
+    mod format {
+        use serenity::all::GuildId;
+        use serenity::prelude::Context;
+        use crate::voice_handler::recording;
+        use super::super::FormatOptions;
+
+        pub async fn run(ctx: &Context, guild_id: GuildId, #[options] args: FormatOptions) -> String {
+            let Some(format) = args.format else {
+                return format!("Recordings are saved as {}", recording::format(ctx, guild_id).await);
+            };
+
+            recording::set_format(ctx, guild_id, format).await;
+            format!("Recordings are saved as {format} from now on")
+        }
     }
 }
 
 #[slash_command]
 #[description("Prints out how much memory the server is using")]
+#[description_localized("de", "Zeigt an, wie viel Speicher der Server verwendet")]
+#[owners_only]
+#[category("Utility")]
 mod get_mem_usage {
     use memory_stats::memory_stats;
+    use serenity::builder::CreateEmbed;
+    use crate::interactions::response::CommandResponse;
 
-    pub fn run() -> String {
-        if let Some(usage) = memory_stats() {
-            format!(
-                "Current physical memory usage: {} MiB\nCurrent virtual memory usage: {} MiB",
-                usage.physical_mem / 1024 / 1024,
-                usage.virtual_mem / 1024 / 1024
-            )
-        } else {
-            "Couldn't get the current memory usage :(".to_owned()
-        }
+    pub fn run() -> CommandResponse {
+        let Some(usage) = memory_stats() else {
+            return CommandResponse::from("Couldn't get the current memory usage :(");
+        };
+
+        let embed = CreateEmbed::new()
+            .title("Memory usage")
+            .field("Physical", format!("{} MiB", usage.physical_mem / 1024 / 1024), true)
+            .field("Virtual", format!("{} MiB", usage.virtual_mem / 1024 / 1024), true);
+
+        CommandResponse::from(embed)
     }
 }
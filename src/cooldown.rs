@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+
+use serenity::client::Context;
+use serenity::model::id::{ ChannelId, GuildId, UserId };
+use serenity::prelude::TypeMapKey;
+use tokio::sync::Mutex;
+
+/// `Bucket` is who a cooldown is shared between.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Bucket {
+    /// Each user has their own cooldown.
+    User,
+
+    /// Everyone in a guild shares one cooldown. Outside of guilds this falls back to the channel.
+    Guild,
+
+    /// Everyone in a channel shares one cooldown.
+    Channel,
+}
+
+/// `Cooldown` is how long a command can't be used again within a bucket after it was used,
+/// declared with `#[cooldown(<user|guild|channel>, <seconds>)]`.
+#[derive(Clone, Copy, Debug)]
+pub struct Cooldown {
+    pub bucket: Bucket,
+    pub duration: Duration,
+}
+
+/// `PrefixCooldown` is submitted by `#[cooldown(..)]` on a prefix `#[command]` function.
+///
+/// Slash commands carry their cooldowns in their registry entry instead.
+pub struct PrefixCooldown {
+    /// The name the command is invoked by.
+    pub command: &'static str,
+    pub cooldown: Cooldown,
+}
+
+inventory::collect!(PrefixCooldown);
+
+/// Returns the cooldowns declared on the prefix command called `command`.
+pub fn prefix_cooldowns(command: &str) -> Vec<Cooldown> {
+    inventory::iter::<PrefixCooldown>
+        .into_iter()
+        .filter(|prefix| prefix.command == command)
+        .map(|prefix| prefix.cooldown)
+        .collect()
+}
+
+/// `Invocation` is who used a command and where, which decides the bucket it falls in.
+#[derive(Clone, Copy, Debug)]
+pub struct Invocation {
+    pub user: UserId,
+    pub guild: Option<GuildId>,
+    pub channel: ChannelId,
+}
+
+impl Invocation {
+    const fn key(&self, bucket: Bucket) -> u64 {
+        match (bucket, self.guild) {
+            (Bucket::User, _) => self.user.get(),
+            (Bucket::Guild, Some(guild)) => guild.get(),
+            (Bucket::Guild, None) | (Bucket::Channel, _) => self.channel.get(),
+        }
+    }
+}
+
+/// `Cooldowns` tracks when each command can next be used in each bucket, shared by slash and
+/// prefix commands through the `TypeMap`.
+#[derive(Default)]
+pub struct Cooldowns {
+    ready_at: Mutex<HashMap<(String, Bucket, u64), Instant>>,
+}
+
+impl TypeMapKey for Cooldowns {
+    type Value = Arc<Self>;
+}
+
+impl Cooldowns {
+    /// Records a use of `command` if none of its cooldowns are running.
+    ///
+    /// # Errors
+    ///
+    /// Returns how much longer the user has to wait if any of them are, in which case the use
+    /// isn't recorded.
+    pub async fn hit(
+        &self,
+        command: &str,
+        cooldowns: &[Cooldown],
+        invocation: Invocation
+    ) -> Result<(), Duration> {
+        if cooldowns.is_empty() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let keys: Vec<_> = cooldowns
+            .iter()
+            .map(|cooldown| (command.to_owned(), cooldown.bucket, invocation.key(cooldown.bucket)))
+            .collect();
+
+        let mut ready_at = self.ready_at.lock().await;
+        ready_at.retain(|_, ready| *ready > now);
+
+        let remaining = keys
+            .iter()
+            .filter_map(|key| ready_at.get(key).map(|ready| ready.duration_since(now)))
+            .max();
+        if remaining.is_none() {
+            for (key, cooldown) in keys.into_iter().zip(cooldowns) {
+                ready_at.insert(key, now + cooldown.duration);
+            }
+        }
+        drop(ready_at);
+
+        remaining.map_or(Ok(()), Err)
+    }
+
+    /// Drops the guild-wide cooldowns running in `guild`, once the bot is no longer in it.
+    pub async fn forget_guild(&self, guild: GuildId) {
+        self.ready_at
+            .lock().await
+            .retain(|(_, bucket, key), _| *bucket != Bucket::Guild || *key != guild.get());
+    }
+}
+
+/// Checks and records a use of `command` against the `Cooldowns` in `ctx.data`.
+///
+/// # Errors
+///
+/// Returns the message to reply with if the command is still on cooldown.
+pub async fn check(
+    ctx: &Context,
+    command: &str,
+    cooldowns: &[Cooldown],
+    invocation: Invocation
+) -> Result<(), String> {
+    let Some(tracker) = ctx.data.read().await.get::<Cooldowns>().cloned() else {
+        return Ok(());
+    };
+
+    tracker
+        .hit(command, cooldowns, invocation).await
+        .map_err(|remaining| {
+            format!("Slow down! You can use this command again in {:.1}s", remaining.as_secs_f32())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serenity::model::id::{ ChannelId, GuildId, UserId };
+
+    use super::{ Bucket, Cooldown, Cooldowns, Invocation };
+
+    fn invocation(user: u64, guild: Option<u64>, channel: u64) -> Invocation {
+        Invocation {
+            user: UserId::new(user),
+            guild: guild.map(GuildId::new),
+            channel: ChannelId::new(channel),
+        }
+    }
+
+    const fn cooldown(bucket: Bucket, duration: Duration) -> [Cooldown; 1] {
+        [Cooldown { bucket, duration }]
+    }
+
+    #[tokio::test]
+    async fn buckets_are_keyed_by_who_they_are_shared_between() {
+        let minute = Duration::from_mins(1);
+        let here = invocation(1, Some(10), 100);
+        // Each case is a bucket, someone sharing it and someone who doesn't
+        let cases = [
+            (Bucket::User, invocation(1, Some(11), 101), invocation(2, Some(10), 100)),
+            (Bucket::Channel, invocation(2, Some(10), 100), invocation(1, Some(10), 101)),
+            (Bucket::Guild, invocation(2, Some(10), 101), invocation(1, Some(11), 100)),
+        ];
+
+        for (bucket, sharing, apart) in cases {
+            let cooldowns = Cooldowns::default();
+            let cooldown = cooldown(bucket, minute);
+
+            assert!(cooldowns.hit("ping", &cooldown, here).await.is_ok(), "{bucket:?}");
+            assert!(cooldowns.hit("ping", &cooldown, sharing).await.is_err(), "{bucket:?}");
+            assert!(cooldowns.hit("ping", &cooldown, apart).await.is_ok(), "{bucket:?}");
+            // Other commands have their own cooldowns
+            assert!(cooldowns.hit("pong", &cooldown, here).await.is_ok(), "{bucket:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn guild_buckets_fall_back_to_the_channel_outside_guilds() {
+        let cooldowns = Cooldowns::default();
+        let cooldown = cooldown(Bucket::Guild, Duration::from_mins(1));
+
+        assert!(cooldowns.hit("ping", &cooldown, invocation(1, None, 100)).await.is_ok());
+        assert!(cooldowns.hit("ping", &cooldown, invocation(2, None, 100)).await.is_err());
+        assert!(cooldowns.hit("ping", &cooldown, invocation(1, None, 101)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn cooldowns_expire() {
+        let cooldowns = Cooldowns::default();
+        let cooldown = cooldown(Bucket::User, Duration::from_millis(50));
+        let here = invocation(1, Some(10), 100);
+
+        assert!(cooldowns.hit("ping", &cooldown, here).await.is_ok());
+        let remaining = cooldowns.hit("ping", &cooldown, here).await.expect_err("still cooling down");
+        assert!(remaining <= Duration::from_millis(50));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(cooldowns.hit("ping", &cooldown, here).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn forgetting_a_guild_only_drops_its_guild_cooldowns() {
+        let cooldowns = Cooldowns::default();
+        let guild = cooldown(Bucket::Guild, Duration::from_mins(1));
+        let user = cooldown(Bucket::User, Duration::from_mins(1));
+        let here = invocation(1, Some(10), 100);
+        let elsewhere = invocation(1, Some(11), 101);
+
+        assert!(cooldowns.hit("ping", &guild, here).await.is_ok());
+        assert!(cooldowns.hit("ping", &guild, elsewhere).await.is_ok());
+        assert!(cooldowns.hit("pong", &user, here).await.is_ok());
+
+        cooldowns.forget_guild(GuildId::new(10)).await;
+
+        assert!(cooldowns.hit("ping", &guild, here).await.is_ok());
+        assert!(cooldowns.hit("ping", &guild, elsewhere).await.is_err());
+        assert!(cooldowns.hit("pong", &user, here).await.is_err());
+    }
+}
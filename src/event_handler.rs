@@ -1,94 +1,407 @@
+use std::env;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::thread;
+use std::time::Duration;
+
 use serenity::async_trait;
-use serenity::builder::{ CreateInteractionResponseMessage, CreateInteractionResponse };
+use serenity::FutureExt;
+use serenity::builder::{
+    AutocompleteChoice,
+    CreateAutocompleteResponse,
+    CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
 
-use serenity::all::{ Interaction, InteractionResponseFlags, Ready };
+use serenity::all::{
+    CommandInteraction,
+    ComponentInteraction,
+    Guild,
+    GuildId,
+    Interaction,
+    InteractionId,
+    ModalInteraction,
+    Ready,
+    UnavailableGuild,
+};
 use serenity::client::{ Context, EventHandler };
 
-use crate::commands::{ self };
+use crate::cooldown::{ self, Cooldowns, Invocation };
+use crate::interactions::components::{ self, ComponentResult, CustomId };
+use crate::interactions::error::{ CommandError, CommandResult };
+use crate::interactions::registry::{ self, Defer, SlashCommand };
+use crate::interactions::response::{ CommandResponse, ComponentResponse };
+use crate::interactions::sync::{ self, Registration, Scope };
+use crate::voice_handler::recording;
+use crate::ShardManagerContainer;
+
+/// Discord drops autocomplete responses that take longer than 3 seconds, so suggestions that
+/// aren't ready by then are given up on in favour of an empty list.
+const AUTOCOMPLETE_DEADLINE: Duration = Duration::from_millis(2500);
+
+/// The most choices Discord accepts in a single autocomplete response.
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+/// How long a command may run before its response is deferred, unless `DEFER_BUDGET_MS` says
+/// otherwise. Discord fails interactions that aren't responded to within 3 seconds.
+const DEFAULT_DEFER_BUDGET: Duration = Duration::from_millis(1500);
+
+pub struct Handler {
+    /// How long a command may run before its response is deferred.
+    defer_budget: Duration,
+
+    /// Where commands are registered.
+    registration: Registration,
+
+    /// What's posted to the system channel of guilds the bot joins, with `{guild}` standing in
+    /// for the guild's name.
+    welcome: Option<String>,
+
+    /// Whether `ready` has been received before, later ones come from reconnects.
+    started: AtomicBool,
+}
+
+impl Handler {
+    /// Creates the handler, reading the defer budget from `DEFER_BUDGET_MS`, where commands are
+    /// registered from `COMMAND_REGISTRATION` and the welcome message from `WELCOME_MESSAGE`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the command registration is misconfigured.
+    pub fn from_env() -> Self {
+        let defer_budget = env
+            ::var("DEFER_BUDGET_MS")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .map_or(DEFAULT_DEFER_BUDGET, Duration::from_millis);
+
+        let registration = Registration::from_env()
+            .unwrap_or_else(|why| panic!("Invalid command registration: {why}"));
+
+        let welcome = env
+            ::var("WELCOME_MESSAGE")
+            .ok()
+            .filter(|message| !message.trim().is_empty());
+
+        Self { defer_budget, registration, welcome, started: AtomicBool::new(false) }
+    }
+
+    /// Syncs the registry's commands into a guild commands are registered in.
+    async fn sync_guild(&self, ctx: &Context, guild: GuildId) {
+        let commands: Vec<_> = registry::commands().collect();
+
+        match sync::sync(&ctx.http, Scope::Guild(guild), &commands).await {
+            Ok(summary) if !summary.is_empty() => println!("Synced {}: {summary}", Scope::Guild(guild)),
+            Ok(_) => {}
+            Err(why) => eprintln!("Couldn't sync commands: {why}"),
+        }
+    }
+
+    /// Posts the welcome message, if there is one, to the system channel of a guild the bot just
+    /// joined.
+    async fn welcome(&self, ctx: &Context, guild: &Guild) {
+        let (Some(welcome), Some(channel)) = (&self.welcome, guild.system_channel_id) else {
+            return;
+        };
+
+        #[allow(clippy::literal_string_with_formatting_args)]
+        let message = welcome.replace("{guild}", &guild.name);
+        if let Err(why) = channel.say(&ctx.http, message).await {
+            println!("Cannot welcome guild {}: {why}", guild.id);
+        }
+    }
+
+    /// Runs a slash command and responds with its result, deferring the response first if the
+    /// command is marked `#[defer]` or is still running once the budget is spent.
+    async fn command(&self, ctx: &Context, command: &CommandInteraction) {
+        let Some(slash_command) = registry::find(command.data.kind, &command.data.name) else {
+            if let Err(why) = CommandResponse::from("not implemented :(").send(ctx, command).await {
+                println!("Cannot respond to slash command: {why}");
+            }
+            return;
+        };
+
+        let invocation = Invocation {
+            user: command.user.id,
+            guild: command.guild_id,
+            channel: command.channel_id,
+        };
+        let allowed = match slash_command.checks.check(ctx, command).await {
+            Ok(()) => {
+                cooldown
+                    ::check(ctx, &command.data.name, slash_command.cooldowns, invocation).await
+                    .map_err(CommandError::User)
+            }
+            Err(why) => Err(why),
+        };
+        if let Err(why) = allowed {
+            if let Err(why) = Self::render(command, Ok(Err(why))).send(ctx, command).await {
+                println!("Cannot respond to slash command: {why}");
+            }
+            return;
+        }
+
+        // A panicking command fails its own interaction rather than the shard running it
+        let mut run = AssertUnwindSafe((slash_command.run)(ctx, command)).catch_unwind();
+
+        let ephemeral = match slash_command.defer {
+            Defer::OnBudget { ephemeral } => {
+                match tokio::time::timeout(self.defer_budget, &mut run).await {
+                    Ok(result) => {
+                        if let Err(why) = Self::render(command, result).send(ctx, command).await {
+                            println!("Cannot respond to slash command: {why}");
+                        }
+                        return;
+                    }
+                    Err(_) => ephemeral,
+                }
+            }
+            Defer::Ephemeral => true,
+            Defer::Public => false,
+        };
+
+        let defer = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(ephemeral)
+        );
+        if let Err(why) = command.create_response(&ctx.http, defer).await {
+            println!("Cannot defer slash command: {why}");
+            return;
+        }
+
+        if let Err(why) = Self::render(command, run.await).edit(ctx, command).await {
+            println!("Cannot edit deferred slash command response: {why}");
+        }
+    }
+
+    /// Turns the outcome of a slash command into its response.
+    fn render(command: &CommandInteraction, result: thread::Result<CommandResult>) -> CommandResponse {
+        let what = format!("/{}", command.data.name);
+        match result {
+            Ok(Ok(response)) => response,
+            Ok(Err(why)) => Self::render_error(command.id, &what, &why),
+            Err(panic) => Self::render_error(command.id, &what, &Self::panicked(&*panic)),
+        }
+    }
+
+    /// Turns an error into its response.
+    ///
+    /// Errors the user can act on are shown to them. Anything else, panics included, is logged
+    /// under the interaction id, which the user is given so the failure can be found later.
+    fn render_error(correlation_id: InteractionId, what: &str, why: &CommandError) -> CommandResponse {
+        if let Some(message) = why.user_message() {
+            return CommandResponse::from(message);
+        }
+
+        println!("[{correlation_id}] {what} failed: {why}");
+
+        CommandResponse::from(
+            format!("Something went wrong running this command, error id: `{correlation_id}`")
+        )
+    }
+
+    /// Turns the payload of a panic into an internal error carrying its message.
+    fn panicked(panic: &(dyn std::any::Any + Send)) -> CommandError {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+
+        CommandError::internal(format!("panicked: {message}"))
+    }
+
+    /// Runs the `#[component("..")]` handler registered under the prefix of the component's
+    /// `custom_id` and responds with its result.
+    async fn component(ctx: &Context, component: &ComponentInteraction) {
+        let custom_id = &component.data.custom_id;
+        let response = match Self::route(component.id, custom_id, components::find_component) {
+            Ok((handler, id)) => {
+                let run = AssertUnwindSafe((handler.run)(ctx, component, id)).catch_unwind().await;
+                Self::render_component(component.id, custom_id, run)
+            }
+            Err(message) => CommandResponse::from(message).into(),
+        };
+
+        if let Err(why) = response.send(ctx, component).await {
+            println!("Cannot respond to component: {why}");
+        }
+    }
+
+    /// Runs the `#[modal("..")]` handler registered under the prefix of the modal's
+    /// `custom_id` and responds with its result.
+    async fn modal(ctx: &Context, modal: &ModalInteraction) {
+        let custom_id = &modal.data.custom_id;
+        let response = match Self::route(modal.id, custom_id, components::find_modal) {
+            Ok((handler, id)) => {
+                let run = AssertUnwindSafe((handler.run)(ctx, modal, id)).catch_unwind().await;
+                Self::render_component(modal.id, custom_id, run)
+            }
+            Err(message) => CommandResponse::from(message).into(),
+        };
+
+        if let Err(why) = response.send_modal(ctx, modal).await {
+            println!("Cannot respond to modal: {why}");
+        }
+    }
+
+    /// Decodes a `custom_id` and finds its handler with `find`, or returns what to tell the user
+    /// if it has none or has expired.
+    fn route<H>(
+        correlation_id: InteractionId,
+        custom_id: &str,
+        find: fn(&str) -> Option<&'static H>
+    ) -> Result<(&'static H, CustomId), &'static str> {
+        let Some((handler, id)) = CustomId::decode(custom_id).and_then(|id| Some((find(id.prefix())?, id))) else {
+            println!("[{correlation_id}] No handler for custom id `{custom_id}`");
+            return Err("This doesn't work anymore");
+        };
+
+        if id.is_expired() {
+            return Err("This has expired");
+        }
+
+        Ok((handler, id))
+    }
+
+    /// Turns the outcome of a component or modal handler into its response.
+    fn render_component(
+        correlation_id: InteractionId,
+        custom_id: &str,
+        result: thread::Result<ComponentResult>
+    ) -> ComponentResponse {
+        let what = format!("`{custom_id}`");
+        match result {
+            Ok(Ok(response)) => response,
+            Ok(Err(why)) => Self::render_error(correlation_id, &what, &why).into(),
+            Err(panic) => Self::render_error(correlation_id, &what, &Self::panicked(&*panic)).into(),
+        }
+    }
 
-pub struct Handler;
+    async fn autocomplete(ctx: &Context, command: &CommandInteraction) {
+        let Some(slash_command) = registry::find(command.data.kind, &command.data.name) else {
+            return;
+        };
+
+        // Whoever can't run the command doesn't get its suggestions either
+        let choices = if slash_command.checks.check(ctx, command).await.is_ok() {
+            Self::suggest(ctx, command, slash_command).await
+        } else {
+            Vec::new()
+        };
+
+        let builder = CreateInteractionResponse::Autocomplete(
+            CreateAutocompleteResponse::new().set_choices(choices)
+        );
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            println!("Cannot respond to autocomplete: {why}");
+        }
+    }
+
+    /// Runs the autocomplete hook of a command, giving up on it once it panics or runs past
+    /// the deadline.
+    async fn suggest(
+        ctx: &Context,
+        command: &CommandInteraction,
+        slash_command: &SlashCommand
+    ) -> Vec<AutocompleteChoice> {
+        let suggest = AssertUnwindSafe((slash_command.autocomplete)(ctx, command)).catch_unwind();
+        let mut choices = match tokio::time::timeout(AUTOCOMPLETE_DEADLINE, suggest).await {
+            Ok(Ok(choices)) => choices,
+            Ok(Err(_)) => {
+                println!("[{}] Autocomplete for {} panicked", command.id, command.data.name);
+                Vec::new()
+            }
+            Err(_) => {
+                println!("[{}] Autocomplete for {} timed out", command.id, command.data.name);
+                Vec::new()
+            }
+        };
+        choices.truncate(MAX_AUTOCOMPLETE_CHOICES);
+        choices
+    }
+}
 
 #[async_trait]
 impl EventHandler for Handler {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         // println!("Received interaction: {interaction:#?}");
 
-        if let Interaction::Command(command) = interaction {
-            // println!("Received command interaction: {command:#?}");
+        match interaction {
+            Interaction::Command(command) => {
+                // println!("Received command interaction: {command:#?}");
 
-            let guild_id = match command.guild_id {
-                Some(id) => id,
-                None => {
-                    println!("This command must be used in a guild.");
-                    return;
-                }
-            };
-
-            let content = match command.data.name.as_str() {
-                "ping" => Some(commands::user::ping::run(&command.data.options())),
-                "id" => {
-                    let result = commands::user::id::run(
-                        &ctx,
-                        guild_id,
-                        &command.data.options()
-                    ).await;
-                    Some(result)
-                }
-                "join_channel" => {
-                    let result = commands::user::join_channel::run(
-                        &ctx,
-                        guild_id,
-                        &command.data.options()
-                    ).await;
-                    Some(result)
-                }
-                "leave_channel" => {
-                    let result = commands::user::leave_channel::run(
-                        &ctx,
-                        guild_id,
-                        &command.data.options()
-                    ).await;
-
-                    Some(result)
-                }
-                "create_meeting" =>
-                    Some(commands::user::create_meeting::run(&command.data.options())),
-                // "help" => {
-                //     // .unwrap_or("Failed to fetch help".to_owned())
-                // }
-                "get_mem_usage" => Some(commands::user::get_mem_usage::run()),
-                _ => Some("not implemented :(".to_string()),
-            };
-
-            if let Some(content) = content {
-                let data = CreateInteractionResponseMessage::new().content(content);
-                let builder = CreateInteractionResponse::Message(
-                    data.flags(InteractionResponseFlags::EPHEMERAL)
-                );
-                if let Err(why) = command.create_response(&ctx.http, builder).await {
-                    println!("Cannot respond to slash command: {why}");
-                }
+                self.command(&ctx, &command).await;
             }
+            Interaction::Autocomplete(command) => Self::autocomplete(&ctx, &command).await,
+            Interaction::Component(component) => Self::component(&ctx, &component).await,
+            Interaction::Modal(modal) => Self::modal(&ctx, &modal).await,
+            _ => {}
         }
     }
 
     async fn ready(&self, ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
 
-        let guilds_cache = ctx.cache.guilds();
-
-        for guild_id in guilds_cache {
-            let _commands = guild_id.set_commands(
-                &ctx.http,
-                vec![
-                    commands::user::ping::register(),
-                    commands::user::id::register(),
-                    commands::user::create_meeting::register(),
-                    commands::user::join_channel::register(),
-                    commands::user::leave_channel::register(),
-                    commands::user::get_mem_usage::register()
-                ]
-            ).await;
+        println!("Syncing commands {}", self.registration);
+        let guilds: Vec<GuildId> = ready.guilds.iter().map(|guild| guild.id).collect();
+        let restarted = self.started.swap(true, Ordering::Relaxed);
+        let Err(why) = sync::sync_all(&ctx.http, &self.registration, &guilds).await else {
+            return;
+        };
+
+        // Commands that fail to register at startup are most likely misconfigured, but a
+        // reconnect may be in the middle of recordings, which are kept going with the commands
+        // registered before
+        if restarted {
+            eprintln!("Couldn't sync commands {}: {why}", self.registration);
+            return;
+        }
+        eprintln!("Couldn't sync commands {}, shutting down: {why}", self.registration);
+        let shard_manager = ctx.data.read().await.get::<ShardManagerContainer>().cloned();
+        if let Some(shard_manager) = shard_manager {
+            shard_manager.shutdown_all().await;
+        }
+    }
+
+    async fn guild_create(&self, ctx: Context, guild: Guild, is_new: Option<bool>) {
+        // Every guild the bot is already in is sent once after `ready` too, those are synced
+        // there
+        if is_new != Some(true) {
+            return;
+        }
+        println!("Joined guild {} ({})", guild.name, guild.id);
+
+        if self.registration.includes(guild.id) {
+            self.sync_guild(&ctx, guild.id).await;
+        }
+        self.welcome(&ctx, &guild).await;
+    }
+
+    async fn guild_delete(&self, ctx: Context, incomplete: UnavailableGuild, _full: Option<Guild>) {
+        // The guild is down, not gone
+        if incomplete.unavailable {
+            return;
+        }
+        let guild = incomplete.id;
+        println!("Left guild {guild}");
+
+        if let Err(why) = recording::stop(&ctx, guild).await {
+            println!("Cannot save the recording of guild {guild}: {why}");
+        }
+        recording::forget_speakers(&ctx, guild).await;
+        recording::forget_format(&ctx, guild).await;
+        if let Some(manager) = songbird::get(&ctx).await {
+            if manager.get(guild).is_some() {
+                if let Err(why) = manager.remove(guild).await {
+                    println!("Cannot leave the voice channel of guild {guild}: {why}");
+                }
+            }
+        }
+        let cooldowns = ctx.data.read().await.get::<Cooldowns>().cloned();
+        if let Some(cooldowns) = cooldowns {
+            cooldowns.forget_guild(guild).await;
         }
+        // Its commands are left registered, the bot can't reach the guild anymore to remove
+        // them, and they're synced again if it's added back
     }
 }
@@ -1,5 +1,6 @@
 use serenity::{ framework::standard::macros::hook, client::Context, all::Message };
 
+use crate::cooldown::{ self, prefix_cooldowns, Invocation };
 use crate::CommandCounter;
 
 /// This is a hook function that gets called before a command is processed.
@@ -17,6 +18,10 @@ use crate::CommandCounter;
 ///
 /// The counter is stored in a `CommandCounter` which is expected to be present in `ctx.data`.
 ///
+/// If the command was given a `#[cooldown(..)]` that is still running, the user is told how long they have to
+/// wait and the command is halted without being counted. These share the `Cooldowns` in `ctx.data` with
+/// slash commands.
+///
 /// If this function returns `false`, the processing of the command is halted.
 ///
 /// # Returns
@@ -42,10 +47,26 @@ use crate::CommandCounter;
 pub async fn before(ctx: &Context, msg: &Message, command_name: &str) -> bool {
     println!("Got command '{}' by user '{}'", command_name, msg.author.name);
 
-    let mut data = ctx.data.write().await;
-    let counter = data.get_mut::<CommandCounter>().expect("Expected CommandCounter in TypeMap.");
-    let entry = counter.entry(command_name.to_string()).or_insert(0);
-    *entry += 1;
+    let invocation = Invocation {
+        user: msg.author.id,
+        guild: msg.guild_id,
+        channel: msg.channel_id,
+    };
+    let cooldowns = prefix_cooldowns(command_name);
+    if let Err(wait) = cooldown::check(ctx, command_name, &cooldowns, invocation).await {
+        if let Err(why) = msg.reply(ctx, wait).await {
+            println!("Error sending cooldown reply: {why:?}");
+        }
+
+        return false;
+    }
+
+    *ctx.data
+        .write().await
+        .get_mut::<CommandCounter>()
+        .expect("Expected CommandCounter in TypeMap.")
+        .entry(command_name.to_string())
+        .or_insert(0) += 1;
 
     true // if `before` returns false, command processing doesn't happen.
 }
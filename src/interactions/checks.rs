@@ -0,0 +1,87 @@
+use serenity::all::{ Channel, ChannelId, CommandInteraction, Permissions };
+use serenity::prelude::Context;
+
+use crate::BotOwners;
+use super::error::CommandError;
+
+/// `Checks` are the restrictions a `#[slash_command]` declares through `#[default_member_permissions(..)]`,
+/// `#[guild_only]`, `#[nsfw]` and `#[owners_only]`.
+///
+/// Discord already hides commands based on the registered fields, but guild admins can
+/// override those, so `Handler` enforces them again before running the command.
+#[derive(Clone, Copy, Debug)]
+pub struct Checks {
+    /// Permissions the member needs in the channel the command is used in.
+    pub permissions: Permissions,
+
+    /// The command can't be used in DMs.
+    pub guild_only: bool,
+
+    /// The command can only be used in age-restricted channels.
+    pub nsfw: bool,
+
+    /// The command can only be used by the bot's owners.
+    pub owners_only: bool,
+}
+
+impl Checks {
+    /// Checks whether the user behind `command` may run it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason they can't, or an HTTP error if the channel couldn't be fetched.
+    pub async fn check(&self, ctx: &Context, command: &CommandInteraction) -> Result<(), CommandError> {
+        if self.owners_only {
+            let is_owner = ctx.data
+                .read().await
+                .get::<BotOwners>()
+                .is_some_and(|owners| owners.contains(&command.user.id));
+
+            if !is_owner {
+                return Err(CommandError::permission("only the bot's owners can use this command"));
+            }
+        }
+
+        if command.guild_id.is_none() {
+            if self.guild_only {
+                return Err(CommandError::user("This command can only be used in a guild."));
+            }
+
+            // Member permissions and age restrictions only apply in guilds
+            return Ok(());
+        }
+
+        if !self.permissions.is_empty() {
+            let granted = command.member
+                .as_ref()
+                .and_then(|member| member.permissions)
+                .unwrap_or_else(Permissions::empty);
+            let missing = self.permissions.difference(granted);
+
+            if !granted.administrator() && !missing.is_empty() {
+                return Err(CommandError::permission(missing.to_string()));
+            }
+        }
+
+        if self.nsfw && !is_nsfw(ctx, command.channel_id).await? {
+            return Err(CommandError::user("This command can only be used in age-restricted channels."));
+        }
+
+        Ok(())
+    }
+}
+
+/// Threads are age-restricted when their parent channel is.
+async fn is_nsfw(ctx: &Context, channel_id: ChannelId) -> Result<bool, CommandError> {
+    let Channel::Guild(channel) = channel_id.to_channel(ctx).await? else {
+        return Ok(false);
+    };
+
+    match (channel.thread_metadata, channel.parent_id) {
+        (Some(_), Some(parent_id)) => {
+            let parent = parent_id.to_channel(ctx).await?;
+            Ok(parent.guild().is_some_and(|parent| parent.nsfw))
+        }
+        _ => Ok(channel.nsfw),
+    }
+}
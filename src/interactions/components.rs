@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+
+use serde::{ de::DeserializeOwned, Serialize };
+use serenity::all::{ ActionRowComponent, ComponentInteraction, ModalInteraction };
+use serenity::futures::future::BoxFuture;
+use serenity::json;
+use serenity::prelude::Context;
+
+use super::error::CommandError;
+use super::response::{ CommandResponse, ComponentResponse };
+
+/// The longest `custom_id` Discord accepts on a component or modal.
+pub const MAX_CUSTOM_ID_LENGTH: usize = 100;
+
+/// `ComponentResult` is what every `#[component("..")]` and `#[modal("..")]` handler runs to.
+pub type ComponentResult = Result<ComponentResponse, CommandError>;
+
+/// `ComponentHandler` is the entry every `#[component("..")]` function submits into the
+/// registry, answering the buttons and select menus whose `custom_id` starts with its prefix.
+pub struct ComponentHandler {
+    /// The `CustomId` prefix the handler answers.
+    pub prefix: &'static str,
+
+    /// Runs the handler for the given interaction and returns what to respond with.
+    pub run: for<'a> fn(&'a Context, &'a ComponentInteraction, CustomId) -> BoxFuture<'a, ComponentResult>,
+}
+
+/// `ModalHandler` is the entry every `#[modal("..")]` function submits into the registry,
+/// answering the submitted modals whose `custom_id` starts with its prefix.
+pub struct ModalHandler {
+    /// The `CustomId` prefix the handler answers.
+    pub prefix: &'static str,
+
+    /// Runs the handler for the given interaction and returns what to respond with.
+    pub run: for<'a> fn(&'a Context, &'a ModalInteraction, CustomId) -> BoxFuture<'a, ComponentResult>,
+}
+
+inventory::collect!(ComponentHandler);
+inventory::collect!(ModalHandler);
+
+/// Looks up the component handler registered under `prefix`.
+pub fn find_component(prefix: &str) -> Option<&'static ComponentHandler> {
+    inventory::iter::<ComponentHandler>.into_iter().find(|handler| handler.prefix == prefix)
+}
+
+/// Looks up the modal handler registered under `prefix`.
+pub fn find_modal(prefix: &str) -> Option<&'static ModalHandler> {
+    inventory::iter::<ModalHandler>.into_iter().find(|handler| handler.prefix == prefix)
+}
+
+/// Checks that no two component handlers, or two modal handlers, share a prefix.
+///
+/// # Errors
+///
+/// Returns the prefixes that were registered more than once.
+pub fn check_duplicates() -> Result<(), Vec<&'static str>> {
+    let mut components = HashSet::new();
+    let mut modals = HashSet::new();
+    let mut duplicates: Vec<&'static str> = inventory::iter::<ComponentHandler>
+        .into_iter()
+        .filter(|handler| !components.insert(handler.prefix))
+        .map(|handler| handler.prefix)
+        .chain(
+            inventory::iter::<ModalHandler>
+                .into_iter()
+                .filter(|handler| !modals.insert(handler.prefix))
+                .map(|handler| handler.prefix)
+        )
+        .collect();
+
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        duplicates.sort_unstable();
+        duplicates.dedup();
+        Err(duplicates)
+    }
+}
+
+/// Returns what was typed into the text input called `custom_id` of a submitted modal, if
+/// anything was.
+pub fn modal_value<'a>(modal: &'a ModalInteraction, custom_id: &str) -> Option<&'a str> {
+    modal.data.components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == custom_id => input.value.as_deref(),
+            _ => None,
+        })
+        .filter(|value| !value.is_empty())
+}
+
+/// `CustomId` is the `custom_id` of a component or modal, which routes it to the handler
+/// registered under its prefix and can carry state for it.
+///
+/// It's encoded as `<prefix>:<expiry>:<payload>`, the expiry being a Unix timestamp in seconds,
+/// left empty if it never expires, and the payload any JSON. Everything has to fit into
+/// Discord's 100 characters, so payloads should be kept to a few ids.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CustomId {
+    prefix: String,
+    expires: Option<u64>,
+    payload: String,
+}
+
+impl CustomId {
+    /// A `CustomId` routed to the handler registered under `prefix`, with no payload that never
+    /// expires.
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            expires: None,
+            payload: String::new(),
+        }
+    }
+
+    /// Sets the state handed to the handler, serialized as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an internal error if `payload` can't be serialized.
+    pub fn with_payload(mut self, payload: &impl Serialize) -> Result<Self, CommandError> {
+        self.payload = json::to_string(payload)
+            .map_err(|why| CommandError::internal(format!("can't serialize a custom id payload: {why}")))?;
+        Ok(self)
+    }
+
+    /// Makes the component stop working after `duration`, answering with an error instead of
+    /// running the handler.
+    #[must_use]
+    pub fn expires_in(mut self, duration: Duration) -> Self {
+        self.expires = Some(now().saturating_add(duration.as_secs()));
+        self
+    }
+
+    /// The prefix of the handler this is routed to.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Whether the expiry has passed.
+    pub fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|expires| expires <= now())
+    }
+
+    /// Deserializes the payload set with `with_payload`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an internal error if the payload isn't a `T`.
+    pub fn payload<T: DeserializeOwned>(&self) -> Result<T, CommandError> {
+        json::from_str(self.payload.as_str())
+            .map_err(|why| CommandError::internal(format!("invalid `{}` payload: {why}", self.prefix)))
+    }
+
+    /// Encodes the `custom_id` to send to Discord.
+    ///
+    /// # Errors
+    ///
+    /// Returns an internal error if it's longer than Discord accepts or the prefix has a `:`.
+    pub fn encode(&self) -> Result<String, CommandError> {
+        if self.prefix.contains(':') {
+            return Err(CommandError::internal(format!("custom id prefix `{}` can't contain `:`", self.prefix)));
+        }
+
+        let expires = self.expires.map(|expires| expires.to_string()).unwrap_or_default();
+        let encoded = format!("{}:{expires}:{}", self.prefix, self.payload);
+        if encoded.chars().count() > MAX_CUSTOM_ID_LENGTH {
+            return Err(
+                CommandError::internal(
+                    format!("custom id `{encoded}` is longer than {MAX_CUSTOM_ID_LENGTH} characters")
+                )
+            );
+        }
+
+        Ok(encoded)
+    }
+
+    /// Decodes a `custom_id` Discord sent back, or returns `None` if it isn't one of ours.
+    pub fn decode(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.splitn(3, ':');
+        let prefix = parts.next()?;
+        let expires = match parts.next()? {
+            "" => None,
+            expires => Some(expires.parse().ok()?),
+        };
+        let payload = parts.next()?;
+
+        Some(Self {
+            prefix: prefix.to_owned(),
+            expires,
+            payload: payload.to_owned(),
+        })
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Conversion from whatever a `#[component("..")]` or `#[modal("..")]` handler returns into a
+/// `ComponentResult`.
+///
+/// Implemented for plain responses, which always succeed, and for `Result`s of them.
+pub trait IntoComponentResult {
+    fn into_component_result(self) -> ComponentResult;
+}
+
+impl IntoComponentResult for ComponentResponse {
+    fn into_component_result(self) -> ComponentResult {
+        Ok(self)
+    }
+}
+
+impl IntoComponentResult for CommandResponse {
+    fn into_component_result(self) -> ComponentResult {
+        Ok(self.into())
+    }
+}
+
+impl<T, E> IntoComponentResult for Result<T, E> where T: Into<ComponentResponse>, E: Into<CommandError> {
+    fn into_component_result(self) -> ComponentResult {
+        self.map(Into::into).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ CustomId, MAX_CUSTOM_ID_LENGTH };
+
+    #[test]
+    fn custom_ids_round_trip() {
+        let id = CustomId::new("meeting_reply")
+            .with_payload(&(1u64, 2u64, true))
+            .expect("a tuple always serializes")
+            .expires_in(Duration::from_mins(5));
+
+        let encoded = id.encode().expect("fits in a custom id");
+        let decoded = CustomId::decode(&encoded).expect("was encoded as a custom id");
+        assert_eq!(decoded, id);
+        assert_eq!(decoded.prefix(), "meeting_reply");
+        assert!(!decoded.is_expired());
+        let payload: (u64, u64, bool) = decoded.payload().expect("was encoded from this type");
+        assert_eq!(payload, (1, 2, true));
+
+        // Without a payload or an expiry
+        let bare = CustomId::new("refresh");
+        assert_eq!(bare.encode().expect("fits in a custom id"), "refresh::");
+        assert_eq!(CustomId::decode("refresh::"), Some(bare));
+    }
+
+    #[test]
+    fn expired_custom_ids_are_rejected() {
+        let id = CustomId::new("meeting_reply").expires_in(Duration::ZERO);
+        let decoded = CustomId::decode(&id.encode().expect("fits in a custom id")).expect("was encoded as a custom id");
+        assert!(decoded.is_expired());
+
+        let decoded = CustomId::decode("meeting_reply:1:").expect("is a custom id");
+        assert!(decoded.is_expired());
+    }
+
+    #[test]
+    fn malformed_custom_ids_are_rejected() {
+        for custom_id in ["", "meeting_reply", "meeting_reply:", "meeting_reply:soon:[]", "meeting_reply:-1:"] {
+            assert_eq!(CustomId::decode(custom_id), None, "{custom_id:?}");
+        }
+
+        let decoded = CustomId::decode("meeting_reply::not json").expect("is a custom id");
+        assert!(decoded.payload::<(u64, u64)>().is_err());
+    }
+
+    #[test]
+    fn oversized_custom_ids_are_an_error() {
+        // Exactly as long as allowed, with the quotes around the JSON string
+        let fits = "a".repeat(MAX_CUSTOM_ID_LENGTH - r#"long::"""#.len());
+        let id = CustomId::new("long").with_payload(&fits).expect("a string always serializes");
+        assert_eq!(id.encode().expect("fits in a custom id").chars().count(), MAX_CUSTOM_ID_LENGTH);
+
+        let too_long = "a".repeat(MAX_CUSTOM_ID_LENGTH);
+        let id = CustomId::new("long").with_payload(&too_long).expect("a string always serializes");
+        assert!(id.encode().is_err());
+
+        assert!(CustomId::new("has:colon").encode().is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use songbird::error::JoinError;
+
+use super::options::OptionError;
+use super::response::CommandResponse;
+
+/// `CommandError` is why a slash command failed.
+///
+/// `Handler` renders it into the response: user and permission errors are shown as they are,
+/// everything else is logged and the user only gets an error id to report.
+#[derive(Debug)]
+pub enum CommandError {
+    /// The command was used wrong, such as with an option that doesn't resolve.
+    User(String),
+
+    /// The user, or the bot, is missing a permission the command needs.
+    Permission(String),
+
+    /// A request to Discord failed.
+    Http(Box<serenity::Error>),
+
+    /// Joining or leaving a voice channel failed.
+    Voice(Box<JoinError>),
+
+    /// Anything else that went wrong inside the bot.
+    Internal(String),
+}
+
+impl CommandError {
+    /// Shorthand for `CommandError::User`.
+    pub fn user(message: impl Into<String>) -> Self {
+        Self::User(message.into())
+    }
+
+    /// Shorthand for `CommandError::Permission`.
+    pub fn permission(message: impl Into<String>) -> Self {
+        Self::Permission(message.into())
+    }
+
+    /// Shorthand for `CommandError::Internal`.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::Internal(message.into())
+    }
+
+    /// The message shown to the user, or `None` if the error should be logged and hidden
+    /// behind an error id.
+    pub fn user_message(&self) -> Option<String> {
+        match self {
+            Self::User(message) => Some(message.clone()),
+            Self::Permission(message) => Some(format!("Missing permissions: {message}")),
+            Self::Voice(why) => match **why {
+                JoinError::NoCall => Some("I'm not in a voice channel".to_owned()),
+                JoinError::TimedOut => {
+                    Some("Timed out connecting to the voice channel, try again later".to_owned())
+                }
+                _ => None,
+            }
+            Self::Http(_) | Self::Internal(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User(message) => write!(f, "{message}"),
+            Self::Permission(message) => write!(f, "missing permissions: {message}"),
+            Self::Http(why) => write!(f, "Discord request failed: {why}"),
+            Self::Voice(why) => write!(f, "voice connection failed: {why}"),
+            Self::Internal(message) => write!(f, "internal error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(why) => Some(&**why),
+            Self::Voice(why) => Some(&**why),
+            Self::User(_) | Self::Permission(_) | Self::Internal(_) => None,
+        }
+    }
+}
+
+impl From<OptionError> for CommandError {
+    fn from(why: OptionError) -> Self {
+        Self::User(why.to_string())
+    }
+}
+
+impl From<serenity::Error> for CommandError {
+    fn from(why: serenity::Error) -> Self {
+        Self::Http(Box::new(why))
+    }
+}
+
+impl From<JoinError> for CommandError {
+    fn from(why: JoinError) -> Self {
+        Self::Voice(Box::new(why))
+    }
+}
+
+/// `CommandResult` is what every `#[slash_command]` runs to.
+pub type CommandResult = Result<CommandResponse, CommandError>;
+
+/// Conversion from whatever a `run` function returns into a `CommandResult`.
+///
+/// Implemented for plain responses, which always succeed, and for `Result`s of them.
+pub trait IntoCommandResult {
+    fn into_command_result(self) -> CommandResult;
+}
+
+impl IntoCommandResult for CommandResponse {
+    fn into_command_result(self) -> CommandResult {
+        Ok(self)
+    }
+}
+
+impl IntoCommandResult for String {
+    fn into_command_result(self) -> CommandResult {
+        Ok(self.into())
+    }
+}
+
+impl IntoCommandResult for &str {
+    fn into_command_result(self) -> CommandResult {
+        Ok(self.into())
+    }
+}
+
+impl<T, E> IntoCommandResult for Result<T, E> where T: Into<CommandResponse>, E: Into<CommandError> {
+    fn into_command_result(self) -> CommandResult {
+        self.map(Into::into).map_err(Into::into)
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serenity::all::{ CommandOption, CommandOptionType, CommandType, Permissions };
+use serenity::builder::{ CreateEmbed, CreateEmbedFooter };
+use serenity::framework::standard::{ Command, CommandGroup, OnlyIn };
+use serenity::json::{ self, Value };
+
+use crate::cooldown::{ prefix_cooldowns, Bucket, Cooldown };
+use crate::{ PREFIX, PREFIX_GROUPS };
+use super::registry::{ self, SlashCommand };
+
+/// The most fields Discord allows in a single embed.
+const MAX_EMBED_FIELDS: usize = 25;
+
+/// `CommandHelp` is everything `/help` shows about a slash or prefix command, gathered from the
+/// command registry and the prefix framework's groups.
+#[derive(Clone, Debug)]
+pub struct CommandHelp {
+    /// What the command is invoked with, `/` or the prefix. Context menu commands have none.
+    pub prefix: &'static str,
+    pub name: String,
+    pub category: String,
+    pub description: String,
+
+    /// Every way of invoking the command, e.g. `/create_meeting user user:<user>`.
+    pub usage: Vec<String>,
+
+    /// A line per option, subcommands' included.
+    pub options: Vec<String>,
+
+    /// Who can use the command and where, cooldowns and aliases.
+    pub notes: Vec<String>,
+}
+
+/// Returns the help of every slash and prefix command, sorted by category and name.
+pub fn commands() -> Vec<CommandHelp> {
+    let mut commands: Vec<_> = registry::commands().map(slash).collect();
+    for group in PREFIX_GROUPS {
+        commands.extend(
+            group.options.commands
+                .iter()
+                .filter(|command| command.options.help_available)
+                .map(|command| prefix(group, command))
+        );
+    }
+
+    commands.sort_by(|a, b| (&a.category, &a.name).cmp(&(&b.category, &b.name)));
+    commands
+}
+
+/// Looks up the help of a command by name, with or without its `/` or prefix.
+pub fn find(name: &str) -> Option<CommandHelp> {
+    let name = name.trim().trim_start_matches('/').trim_start_matches(PREFIX);
+
+    commands().into_iter().find(|command| command.name == name)
+}
+
+/// Renders the command list as embeds, a page per category. Categories with more commands than
+/// fit in one embed are spread over several pages.
+pub fn pages() -> Vec<CreateEmbed> {
+    let mut categories: BTreeMap<String, Vec<CommandHelp>> = BTreeMap::new();
+    for command in commands() {
+        categories.entry(command.category.clone()).or_default().push(command);
+    }
+
+    let chunks: Vec<_> = categories
+        .iter()
+        .flat_map(|(category, commands)| {
+            commands.chunks(MAX_EMBED_FIELDS).map(move |chunk| (category, chunk))
+        })
+        .collect();
+    let total = chunks.len();
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, (category, commands))| {
+            let fields = commands.iter().map(|command| {
+                (format!("{}{}", command.prefix, command.name), command.description.clone(), false)
+            });
+
+            CreateEmbed::new()
+                .title(format!("{category} commands"))
+                .fields(fields)
+                .footer(
+                    CreateEmbedFooter::new(
+                        format!("Page {}/{total} - /help command:<name> for details", i + 1)
+                    )
+                )
+        })
+        .collect()
+}
+
+/// Renders the detailed help of a single command.
+pub fn details(command: &CommandHelp) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title(format!("{}{}", command.prefix, command.name))
+        .description(&command.description)
+        .field("Usage", format!("```\n{}\n```", command.usage.join("\n")), false);
+
+    if !command.options.is_empty() {
+        embed = embed.field("Options", command.options.join("\n"), false);
+    }
+    if !command.notes.is_empty() {
+        embed = embed.field("Notes", command.notes.join("\n"), false);
+    }
+
+    embed.footer(CreateEmbedFooter::new(format!("Category: {}", command.category)))
+}
+
+fn slash(command: &SlashCommand) -> CommandHelp {
+    // The registered builder is the one source of truth for descriptions and options,
+    // including those of `#[derive(SlashOptions)]` structs and subcommands
+    let mut registered = json::to_value((command.register)()).unwrap_or_default();
+    let description = registered
+        .get("description")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned();
+    let options: Vec<CommandOption> = registered
+        .get_mut("options")
+        .map(Value::take)
+        .and_then(|options| json::from_value(options).ok())
+        .unwrap_or_default();
+
+    let mut help = CommandHelp {
+        prefix: "/",
+        name: command.name.to_owned(),
+        category: command.category().to_owned(),
+        description,
+        usage: Vec::new(),
+        options: Vec::new(),
+        notes: Vec::new(),
+    };
+    match command.kind {
+        CommandType::User | CommandType::Message => {
+            let target = if command.kind == CommandType::User { "user" } else { "message" };
+            help.prefix = "";
+            help.description = format!("Used on a {target} from its Apps menu");
+            help.usage.push(format!("Right-click a {target} > Apps > {}", command.name));
+        }
+        _ => describe_options(&format!("/{}", command.name), &options, &mut help),
+    }
+
+    let checks = &command.checks;
+    help.notes.extend(permissions_note(checks.permissions));
+    if checks.guild_only {
+        help.notes.push("Only usable in guilds".to_owned());
+    }
+    if checks.owners_only {
+        help.notes.push("Only usable by the bot's owners".to_owned());
+    }
+    if checks.nsfw {
+        help.notes.push("Only usable in age-restricted channels".to_owned());
+    }
+    help.notes.extend(command.cooldowns.iter().map(cooldown_note));
+
+    help
+}
+
+fn prefix(group: &CommandGroup, command: &Command) -> CommandHelp {
+    let options = command.options;
+    let name = options.names.first().copied().unwrap_or_default();
+
+    let mut usage = format!("{PREFIX}{name}");
+    if let Some(args) = options.usage {
+        let _ = write!(usage, " {args}");
+    }
+
+    // The framework folds doc comments into the description as well, ahead of the
+    // `#[description = ".."]` line, so only that last line is shown
+    let description = options.desc
+        .and_then(|desc| desc.lines().rev().find(|line| !line.trim().is_empty()))
+        .unwrap_or("No description");
+
+    let mut notes = Vec::new();
+    if options.names.len() > 1 {
+        notes.push(format!("Aliases: {}", options.names[1..].join(", ")));
+    }
+    notes.extend(permissions_note(options.required_permissions | group.options.required_permissions));
+    match (options.only_in, group.options.only_in) {
+        (OnlyIn::Guild, _) | (OnlyIn::None, OnlyIn::Guild) => {
+            notes.push("Only usable in guilds".to_owned());
+        }
+        (OnlyIn::Dm, _) | (OnlyIn::None, OnlyIn::Dm) => notes.push("Only usable in DMs".to_owned()),
+        _ => {}
+    }
+    if options.owners_only || group.options.owners_only {
+        notes.push("Only usable by the bot's owners".to_owned());
+    }
+    notes.extend(prefix_cooldowns(name).iter().map(cooldown_note));
+
+    CommandHelp {
+        prefix: PREFIX,
+        name: name.to_owned(),
+        category: group.name.to_owned(),
+        description: description.to_owned(),
+        usage: vec![usage],
+        options: Vec::new(),
+        notes,
+    }
+}
+
+/// Adds a usage line for every leaf of the command tree below `path`, and a line for every
+/// option along the way.
+fn describe_options(path: &str, options: &[CommandOption], help: &mut CommandHelp) {
+    let subcommands: Vec<_> = options
+        .iter()
+        .filter(|option| {
+            matches!(option.kind, CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup)
+        })
+        .collect();
+
+    if !subcommands.is_empty() {
+        for subcommand in subcommands {
+            describe_options(&format!("{path} {}", subcommand.name), &subcommand.options, help);
+        }
+        return;
+    }
+
+    let mut usage = path.to_owned();
+    for option in options {
+        let kind = option_kind(option.kind);
+        if option.required {
+            let _ = write!(usage, " {}:<{kind}>", option.name);
+        } else {
+            let _ = write!(usage, " [{}:<{kind}>]", option.name);
+        }
+
+        let optional = if option.required { "" } else { " (optional)" };
+        help.options.push(format!("`{}` - {}{optional}", option.name, option.description));
+    }
+    help.usage.push(usage);
+}
+
+const fn option_kind(kind: CommandOptionType) -> &'static str {
+    match kind {
+        CommandOptionType::String => "text",
+        CommandOptionType::Integer => "integer",
+        CommandOptionType::Number => "number",
+        CommandOptionType::Boolean => "true|false",
+        CommandOptionType::User => "user",
+        CommandOptionType::Channel => "channel",
+        CommandOptionType::Role => "role",
+        CommandOptionType::Mentionable => "mentionable",
+        CommandOptionType::Attachment => "file",
+        _ => "value",
+    }
+}
+
+fn permissions_note(permissions: Permissions) -> Option<String> {
+    (!permissions.is_empty()).then(|| format!("Needs the {permissions} permissions"))
+}
+
+fn cooldown_note(cooldown: &Cooldown) -> String {
+    let bucket = match cooldown.bucket {
+        Bucket::User => "user",
+        Bucket::Guild => "guild",
+        Bucket::Channel => "channel",
+    };
+
+    format!("Cooldown of {}s per {bucket}", cooldown.duration.as_secs())
+}
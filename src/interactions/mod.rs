@@ -0,0 +1,8 @@
+pub mod checks;
+pub mod components;
+pub mod error;
+pub mod help;
+pub mod options;
+pub mod registry;
+pub mod response;
+pub mod sync;
@@ -0,0 +1,371 @@
+use std::fmt;
+
+use serenity::all::{
+    Attachment,
+    ChannelId,
+    CommandOptionType,
+    CreateCommandOption,
+    PartialChannel,
+    ResolvedOption,
+    ResolvedValue,
+    Role,
+    RoleId,
+    Unresolved,
+    User,
+    UserId,
+};
+
+/// `OptionError` describes why a slash command option could not be turned into the type
+/// the command asked for.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OptionError {
+    /// A required option was not sent with the interaction.
+    Missing { name: String },
+
+    /// The option was sent, but its value is not of the expected type.
+    InvalidType { name: String, expected: &'static str },
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { name } => write!(f, "Missing required option `{name}`"),
+            Self::InvalidType { name, expected } => {
+                write!(f, "Option `{name}` should be {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OptionError {}
+
+/// Conversion from a resolved option value into a typed value.
+///
+/// This is what lets a `#[slash_command]` `run` function take a `ChannelId`, `Role` or `i64`
+/// argument directly instead of matching on `ResolvedValue` itself.
+pub trait FromResolvedValue<'a>: Sized {
+    /// The option type `Self` is registered as by `#[derive(SlashOptions)]`.
+    const KIND: CommandOptionType;
+
+    /// Human readable name of the expected value, used in `OptionError::InvalidType`.
+    const EXPECTED: &'static str;
+
+    /// Returns `None` if the value is of a different kind than `Self`.
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self>;
+
+    /// Adds anything else `Self` needs on its registered option, such as a list of choices.
+    fn decorate(option: CreateCommandOption) -> CreateCommandOption {
+        option
+    }
+}
+
+/// A set of options parsed as a whole, generated by `#[derive(SlashOptions)]`.
+pub trait SlashOptions {
+    /// The options to register on the command, in declaration order.
+    fn create_options() -> Vec<CreateCommandOption>;
+}
+
+impl<'a> FromResolvedValue<'a> for String {
+    const KIND: CommandOptionType = CommandOptionType::String;
+    const EXPECTED: &'static str = "text";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::String(value) | ResolvedValue::Autocomplete { value, .. } => {
+                Some((*value).to_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for &'a str {
+    const KIND: CommandOptionType = CommandOptionType::String;
+    const EXPECTED: &'static str = "text";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::String(value) | ResolvedValue::Autocomplete { value, .. } => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for i64 {
+    const KIND: CommandOptionType = CommandOptionType::Integer;
+    const EXPECTED: &'static str = "a whole number";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for f64 {
+    const KIND: CommandOptionType = CommandOptionType::Number;
+    const EXPECTED: &'static str = "a number";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::Number(value) => Some(*value),
+            #[allow(clippy::cast_precision_loss)]
+            ResolvedValue::Integer(value) => Some(*value as Self),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for bool {
+    const KIND: CommandOptionType = CommandOptionType::Boolean;
+    const EXPECTED: &'static str = "true or false";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for ChannelId {
+    const KIND: CommandOptionType = CommandOptionType::Channel;
+    const EXPECTED: &'static str = "a channel";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::Channel(channel) => Some(channel.id),
+            ResolvedValue::Unresolved(Unresolved::Channel(id)) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for &'a PartialChannel {
+    const KIND: CommandOptionType = CommandOptionType::Channel;
+    const EXPECTED: &'static str = "a channel";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::Channel(channel) => Some(channel),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for RoleId {
+    const KIND: CommandOptionType = CommandOptionType::Role;
+    const EXPECTED: &'static str = "a role";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::Role(role) => Some(role.id),
+            ResolvedValue::Unresolved(Unresolved::RoleId(id)) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for Role {
+    const KIND: CommandOptionType = CommandOptionType::Role;
+    const EXPECTED: &'static str = "a role";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::Role(role) => Some((*role).clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for UserId {
+    const KIND: CommandOptionType = CommandOptionType::User;
+    const EXPECTED: &'static str = "a user";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::User(user, _) => Some(user.id),
+            ResolvedValue::Unresolved(Unresolved::User(id)) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for User {
+    const KIND: CommandOptionType = CommandOptionType::User;
+    const EXPECTED: &'static str = "a user";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::User(user, _) => Some((*user).clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromResolvedValue<'a> for Attachment {
+    const KIND: CommandOptionType = CommandOptionType::Attachment;
+    const EXPECTED: &'static str = "an attachment";
+
+    fn from_resolved(value: &ResolvedValue<'a>) -> Option<Self> {
+        match value {
+            ResolvedValue::Attachment(attachment) => Some((*attachment).clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Looks up the option called `name` and converts it into `T`.
+///
+/// # Errors
+///
+/// Returns `OptionError::Missing` if no option with that name was sent and
+/// `OptionError::InvalidType` if its value can't be converted into `T`.
+pub fn required<'a, T: FromResolvedValue<'a>>(
+    options: &[ResolvedOption<'a>],
+    name: &str
+) -> Result<T, OptionError> {
+    optional(options, name)?.ok_or_else(|| OptionError::Missing { name: name.to_owned() })
+}
+
+/// Looks up the option called `name` and converts it into `T`, if it was sent at all.
+///
+/// # Errors
+///
+/// Returns `OptionError::InvalidType` if the option was sent but its value can't be
+/// converted into `T`.
+pub fn optional<'a, T: FromResolvedValue<'a>>(
+    options: &[ResolvedOption<'a>],
+    name: &str
+) -> Result<Option<T>, OptionError> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .map(|option| {
+            T::from_resolved(&option.value).ok_or_else(|| OptionError::InvalidType {
+                name: name.to_owned(),
+                expected: T::EXPECTED,
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use greys_macros::{ SlashChoice, SlashOptions };
+    use serenity::all::{ ChannelId, CommandInteraction, RoleId };
+    use serenity::json::{ self, json, Value };
+
+    use super::{ optional, required, OptionError };
+
+    #[derive(Debug, PartialEq, Eq, SlashChoice)]
+    enum Format {
+        Text,
+        #[name("at")]
+        Mentions,
+    }
+
+    #[derive(Debug, SlashOptions)]
+    struct Options {
+        #[description("The role to look up")]
+        role: RoleId,
+
+        #[description("How many to list")]
+        limit: Option<i64>,
+
+        #[description("How to list them")]
+        format: Option<Format>,
+    }
+
+    /// A `/test` interaction sent with `options`.
+    fn interaction(options: &Value) -> CommandInteraction {
+        json::from_value(json!({
+            "id": "1",
+            "application_id": "2",
+            "type": 2,
+            "token": "token",
+            "version": 1,
+            "locale": "en-US",
+            "channel_id": "3",
+            "user": { "id": "4", "username": "someone", "discriminator": "0", "avatar": null },
+            "data": { "id": "5", "name": "test", "type": 1, "options": options },
+        }))
+        .expect("is a valid interaction")
+    }
+
+    #[test]
+    fn options_are_parsed_by_name() {
+        let command = interaction(&json!([
+            { "name": "format", "type": 3, "value": "at" },
+            { "name": "role", "type": 8, "value": "10" },
+            { "name": "limit", "type": 4, "value": 5 },
+        ]));
+        let options = command.data.options();
+
+        let parsed = Options::try_from(options.as_slice()).expect("every option is valid");
+        assert_eq!(parsed.role, RoleId::new(10));
+        assert_eq!(parsed.limit, Some(5));
+        assert_eq!(parsed.format, Some(Format::Mentions));
+    }
+
+    #[test]
+    fn optional_options_can_be_left_out() {
+        let command = interaction(&json!([{ "name": "role", "type": 8, "value": "10" }]));
+        let options = command.data.options();
+
+        let parsed = Options::try_from(options.as_slice()).expect("every option is valid");
+        assert_eq!(parsed.limit, None);
+        assert_eq!(parsed.format, None);
+        assert_eq!(optional::<ChannelId>(&options, "channel"), Ok(None));
+    }
+
+    #[test]
+    fn required_options_have_to_be_sent() {
+        let command = interaction(&json!([{ "name": "limit", "type": 4, "value": 5 }]));
+        let options = command.data.options();
+
+        let missing = OptionError::Missing { name: "role".to_owned() };
+        assert_eq!(Options::try_from(options.as_slice()).expect_err("role is missing"), missing);
+        assert_eq!(required::<RoleId>(&options, "role"), Err(missing));
+    }
+
+    #[test]
+    fn values_of_the_wrong_type_are_rejected() {
+        let command = interaction(&json!([
+            { "name": "role", "type": 8, "value": "10" },
+            { "name": "limit", "type": 3, "value": "five" },
+        ]));
+        let options = command.data.options();
+
+        assert_eq!(
+            Options::try_from(options.as_slice()).expect_err("limit isn't a number"),
+            OptionError::InvalidType { name: "limit".to_owned(), expected: "a whole number" }
+        );
+        assert_eq!(
+            required::<String>(&options, "role"),
+            Err(OptionError::InvalidType { name: "role".to_owned(), expected: "text" })
+        );
+        assert_eq!(required::<String>(&options, "limit").as_deref(), Ok("five"));
+    }
+
+    #[test]
+    fn choices_map_to_their_variants() {
+        for (value, format) in [("text", Some(Format::Text)), ("at", Some(Format::Mentions))] {
+            let command = interaction(&json!([{ "name": "format", "type": 3, "value": value }]));
+            assert_eq!(optional(&command.data.options(), "format"), Ok(format), "{value}");
+        }
+
+        // Variant names aren't values once renamed, and nothing else is either
+        for value in ["mentions", "Text", ""] {
+            let command = interaction(&json!([{ "name": "format", "type": 3, "value": value }]));
+            assert_eq!(
+                optional::<Format>(&command.data.options(), "format"),
+                Err(OptionError::InvalidType { name: "format".to_owned(), expected: "one of the listed choices" }),
+                "{value}"
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use serenity::all::{ CommandInteraction, CommandType };
+use serenity::builder::{ AutocompleteChoice, CreateCommand };
+use serenity::futures::future::BoxFuture;
+use serenity::prelude::Context;
+
+use crate::cooldown::Cooldown;
+use super::checks::Checks;
+use super::error::CommandResult;
+
+/// `SlashCommand` is the entry every `#[slash_command]`, `#[user_command]` and
+/// `#[message_command]` module submits into the registry.
+///
+/// Both command registration, synced in `Handler::ready`, and dispatch in
+/// `Handler::interaction_create` are driven from these entries, so adding a command never means
+/// touching the handler.
+pub struct SlashCommand {
+    /// The name the command is registered and dispatched under.
+    pub name: &'static str,
+
+    /// Whether this is a slash command or shows up on the Apps menu of users or messages.
+    pub kind: CommandType,
+
+    /// Builds the command as it's registered with Discord.
+    pub register: fn() -> CreateCommand,
+
+    /// Runs the command for the given interaction and returns what to respond with.
+    pub run: for<'a> fn(&'a Context, &'a CommandInteraction) -> BoxFuture<'a, CommandResult>,
+
+    /// Suggests values for the option the user is typing into, through the command's
+    /// `#[autocomplete(option = "..")]` hooks.
+    pub autocomplete: for<'a> fn(
+        &'a Context,
+        &'a CommandInteraction
+    ) -> BoxFuture<'a, Vec<AutocompleteChoice>>,
+
+    /// Whether the response is deferred before the command runs.
+    pub defer: Defer,
+
+    /// Who may run the command and where, checked before it runs.
+    pub checks: Checks,
+
+    /// The `#[cooldown(..)]`s of the command, checked after `checks`.
+    pub cooldowns: &'static [Cooldown],
+
+    /// The `/help` category set with `#[category("..")]`.
+    pub category: Option<&'static str>,
+
+    /// The path of the module generated for the command.
+    pub module: &'static str,
+}
+
+impl SlashCommand {
+    /// The `/help` category of the command, which is the file it is declared in unless
+    /// `#[category("..")]` says otherwise.
+    pub fn category(&self) -> &'static str {
+        self.category.unwrap_or_else(|| self.module.rsplit("::").nth(1).unwrap_or(self.module))
+    }
+}
+
+/// `Defer` is when a command's response is deferred, set with `#[defer]` on the module.
+///
+/// Deferring shows a "thinking" message and buys the command 15 minutes instead of Discord's
+/// 3 seconds, after which the original response is edited with the result. Whether that
+/// response is ephemeral is decided when deferring, not by the `CommandResponse`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Defer {
+    /// Deferred only once the command runs past the handler's budget, ephemerally unless
+    /// `#[defer(on_budget, public)]` says otherwise.
+    OnBudget { ephemeral: bool },
+
+    /// `#[defer]` - always deferred with an ephemeral response.
+    Ephemeral,
+
+    /// `#[defer(public)]` - always deferred with a response everyone can see.
+    Public,
+}
+
+inventory::collect!(SlashCommand);
+
+/// Returns every registered slash command.
+pub fn commands() -> impl Iterator<Item = &'static SlashCommand> {
+    inventory::iter::<SlashCommand>.into_iter()
+}
+
+/// Looks up a registered command by kind and name. Discord lets a slash command and a context
+/// menu command share a name, so both are needed.
+pub fn find(kind: CommandType, name: &str) -> Option<&'static SlashCommand> {
+    commands().find(|command| command.kind == kind && command.name == name)
+}
+
+/// Checks that no two registered commands of the same kind share a name.
+///
+/// # Errors
+///
+/// Returns the names that were registered more than once.
+pub fn check_duplicates() -> Result<(), Vec<&'static str>> {
+    let mut seen = HashSet::new();
+    let mut duplicates: Vec<&'static str> = commands()
+        .filter(|command| !seen.insert((command.kind, command.name)))
+        .map(|command| command.name)
+        .collect();
+
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        duplicates.sort_unstable();
+        duplicates.dedup();
+        Err(duplicates)
+    }
+}
@@ -0,0 +1,331 @@
+use std::future::Future;
+use std::mem;
+
+use serenity::all::{ CommandInteraction, ComponentInteraction, Message, ModalInteraction };
+use serenity::builder::{
+    CreateActionRow,
+    CreateAttachment,
+    CreateEmbed,
+    CreateInteractionResponse,
+    CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage,
+    CreateModal,
+    EditInteractionResponse,
+};
+use serenity::prelude::Context;
+
+/// `CommandResponse` is what a slash command answers its interaction with.
+///
+/// `run` functions can keep returning a `String`, which becomes an ephemeral text response,
+/// or build one of these to send embeds, attachments, buttons, a public message or follow-ups.
+#[derive(Clone, Debug)]
+pub struct CommandResponse {
+    content: Option<String>,
+    embeds: Vec<CreateEmbed>,
+    attachments: Vec<CreateAttachment>,
+    components: Vec<CreateActionRow>,
+    ephemeral: bool,
+    follow_ups: Vec<Self>,
+}
+
+impl Default for CommandResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandResponse {
+    /// An empty, ephemeral response.
+    pub const fn new() -> Self {
+        Self {
+            content: None,
+            embeds: Vec::new(),
+            attachments: Vec::new(),
+            components: Vec::new(),
+            ephemeral: true,
+            follow_ups: Vec::new(),
+        }
+    }
+
+    /// Sets the text of the message.
+    #[must_use]
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+
+    /// Adds an embed to the message.
+    #[must_use]
+    pub fn embed(mut self, embed: CreateEmbed) -> Self {
+        self.embeds.push(embed);
+        self
+    }
+
+    /// Attaches a file to the message.
+    #[must_use]
+    pub fn attachment(mut self, attachment: CreateAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Adds a row of buttons, or a select menu, to the message. Their `custom_id`s are routed
+    /// to `#[component("..")]` handlers.
+    #[must_use]
+    pub fn action_row(mut self, row: CreateActionRow) -> Self {
+        self.components.push(row);
+        self
+    }
+
+    /// Sets whether only the user running the command can see the message. Responses are
+    /// ephemeral unless told otherwise.
+    #[must_use]
+    pub const fn ephemeral(mut self, ephemeral: bool) -> Self {
+        self.ephemeral = ephemeral;
+        self
+    }
+
+    /// Queues another message to be sent once this one is. Follow-ups of `response` are sent
+    /// right after it.
+    #[must_use]
+    pub fn follow_up(mut self, mut response: Self) -> Self {
+        let nested = mem::take(&mut response.follow_ups);
+        self.follow_ups.push(response);
+        self.follow_ups.extend(nested);
+        self
+    }
+
+    /// Sends the response to `command`, followed by its follow-ups in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error Discord responds with. Follow-ups aren't sent if the response itself
+    /// fails, but one failing doesn't stop the ones after it.
+    pub async fn send(self, ctx: &Context, command: &CommandInteraction) -> serenity::Result<()> {
+        let (message, follow_ups) = self.into_message();
+        command.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await?;
+
+        send_follow_ups(follow_ups, |follow_up| command.create_followup(&ctx.http, follow_up)).await
+    }
+
+    /// Edits a deferred response to `command` into this one, then sends its follow-ups in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error Discord responds with. Follow-ups aren't sent if the response itself
+    /// fails, but one failing doesn't stop the ones after it.
+    pub async fn edit(self, ctx: &Context, command: &CommandInteraction) -> serenity::Result<()> {
+        let mut edit = EditInteractionResponse::new().embeds(self.embeds).components(self.components);
+        if let Some(content) = self.content {
+            edit = edit.content(content);
+        }
+        for attachment in self.attachments {
+            edit = edit.new_attachment(attachment);
+        }
+        command.edit_response(&ctx.http, edit).await?;
+
+        send_follow_ups(self.follow_ups, |follow_up| command.create_followup(&ctx.http, follow_up)).await
+    }
+
+    fn into_message(self) -> (CreateInteractionResponseMessage, Vec<Self>) {
+        let mut message = CreateInteractionResponseMessage::new()
+            .embeds(self.embeds)
+            .files(self.attachments)
+            .components(self.components)
+            .ephemeral(self.ephemeral);
+        if let Some(content) = self.content {
+            message = message.content(content);
+        }
+
+        (message, self.follow_ups)
+    }
+
+    fn into_followup(self) -> CreateInteractionResponseFollowup {
+        let mut followup = CreateInteractionResponseFollowup::new()
+            .embeds(self.embeds)
+            .files(self.attachments)
+            .components(self.components)
+            .ephemeral(self.ephemeral);
+        if let Some(content) = self.content {
+            followup = followup.content(content);
+        }
+
+        followup
+    }
+}
+
+impl From<String> for CommandResponse {
+    fn from(content: String) -> Self {
+        Self::new().content(content)
+    }
+}
+
+impl From<&str> for CommandResponse {
+    fn from(content: &str) -> Self {
+        Self::new().content(content)
+    }
+}
+
+impl From<CreateEmbed> for CommandResponse {
+    fn from(embed: CreateEmbed) -> Self {
+        Self::new().embed(embed)
+    }
+}
+
+/// `ComponentResponse` is what a `#[component("..")]` or `#[modal("..")]` handler answers its
+/// interaction with.
+#[derive(Clone, Debug)]
+pub enum ComponentResponse {
+    /// Sends a new message, as a command would.
+    Message(CommandResponse),
+
+    /// Replaces the message the component is on. Its buttons are removed unless the response
+    /// adds them again, and whether it's ephemeral can't be changed.
+    Update(CommandResponse),
+
+    /// Opens a modal. Discord only allows this in answer to a component, not to another modal.
+    Modal(CreateModal),
+}
+
+impl ComponentResponse {
+    /// Sends the response to `component`, followed by its follow-ups in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error Discord responds with. Follow-ups aren't sent if the response itself
+    /// fails, but one failing doesn't stop the ones after it.
+    pub async fn send(self, ctx: &Context, component: &ComponentInteraction) -> serenity::Result<()> {
+        let (response, follow_ups) = self.into_response();
+        component.create_response(&ctx.http, response).await?;
+
+        send_follow_ups(follow_ups, |follow_up| component.create_followup(&ctx.http, follow_up)).await
+    }
+
+    /// Sends the response to a submitted `modal`, followed by its follow-ups in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error Discord responds with. Follow-ups aren't sent if the response itself
+    /// fails, but one failing doesn't stop the ones after it.
+    pub async fn send_modal(self, ctx: &Context, modal: &ModalInteraction) -> serenity::Result<()> {
+        let (response, follow_ups) = self.into_response();
+        modal.create_response(&ctx.http, response).await?;
+
+        send_follow_ups(follow_ups, |follow_up| modal.create_followup(&ctx.http, follow_up)).await
+    }
+
+    fn into_response(self) -> (CreateInteractionResponse, Vec<CommandResponse>) {
+        match self {
+            Self::Message(response) => {
+                let (message, follow_ups) = response.into_message();
+                (CreateInteractionResponse::Message(message), follow_ups)
+            }
+            Self::Update(response) => {
+                // The message keeps whoever could see it before
+                let (message, follow_ups) = response.ephemeral(false).into_message();
+                (CreateInteractionResponse::UpdateMessage(message), follow_ups)
+            }
+            Self::Modal(modal) => (CreateInteractionResponse::Modal(modal), Vec::new()),
+        }
+    }
+}
+
+impl From<CommandResponse> for ComponentResponse {
+    fn from(response: CommandResponse) -> Self {
+        Self::Message(response)
+    }
+}
+
+impl From<String> for ComponentResponse {
+    fn from(content: String) -> Self {
+        Self::Message(content.into())
+    }
+}
+
+impl From<&str> for ComponentResponse {
+    fn from(content: &str) -> Self {
+        Self::Message(content.into())
+    }
+}
+
+impl From<CreateModal> for ComponentResponse {
+    fn from(modal: CreateModal) -> Self {
+        Self::Modal(modal)
+    }
+}
+
+/// Sends `follow_ups` in order with `send`, carrying on past any that fail, such as one with a
+/// file Discord won't take, and returning the first error.
+async fn send_follow_ups<F, Fut>(follow_ups: Vec<CommandResponse>, send: F) -> serenity::Result<()>
+where
+    F: Fn(CreateInteractionResponseFollowup) -> Fut,
+    Fut: Future<Output = serenity::Result<Message>>,
+{
+    let mut first_error = Ok(());
+    for follow_up in follow_ups {
+        if let Err(why) = send(follow_up.into_followup()).await {
+            first_error = first_error.and(Err(why));
+        }
+    }
+
+    first_error
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serenity::all::Message;
+    use serenity::json;
+
+    use super::{ send_follow_ups, CommandResponse };
+
+    #[test]
+    fn follow_ups_are_sent_in_order() {
+        let response = CommandResponse::from("first")
+            .follow_up(CommandResponse::from("second").follow_up(CommandResponse::from("third")))
+            .follow_up(CommandResponse::from("fourth"));
+
+        let (message, follow_ups) = response.into_message();
+        let message = json::to_value(message).expect("a message always serializes");
+        assert_eq!(message["content"], "first");
+
+        let contents: Vec<_> = follow_ups.iter().map(|follow_up| follow_up.content.as_deref()).collect();
+        assert_eq!(contents, [Some("second"), Some("third"), Some("fourth")]);
+        assert!(follow_ups.iter().all(|follow_up| follow_up.follow_ups.is_empty()));
+    }
+
+    #[test]
+    fn follow_ups_keep_their_own_visibility() {
+        let response = CommandResponse::from("public")
+            .ephemeral(false)
+            .follow_up(CommandResponse::from("private"));
+
+        let (message, follow_ups) = response.into_message();
+        let message = json::to_value(message).expect("a message always serializes");
+        // 64 is the ephemeral flag
+        assert_eq!(message["flags"].as_u64().unwrap_or_default() & 64, 0);
+        assert!(follow_ups[0].ephemeral);
+    }
+
+    #[tokio::test]
+    async fn failed_follow_ups_dont_hold_back_the_rest() {
+        let follow_ups = ["first", "second", "third"].map(CommandResponse::from).to_vec();
+        let sent = Mutex::new(Vec::new());
+
+        let result = send_follow_ups(follow_ups, |follow_up| {
+            let content = json::to_value(&follow_up).expect("a follow-up always serializes")["content"].clone();
+            sent.lock().expect("not poisoned").push(content.clone());
+            async move {
+                if content == "first" {
+                    Err(serenity::Error::Other("too big"))
+                } else {
+                    Ok(Message::default())
+                }
+            }
+        })
+        .await;
+
+        assert!(matches!(result, Err(serenity::Error::Other("too big"))));
+        assert_eq!(sent.into_inner().expect("not poisoned"), ["first", "second", "third"]);
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+
+use serenity::all::{ Command, CommandId, CommandType, GuildId };
+use serenity::builder::CreateCommand;
+use serenity::http::Http;
+use serenity::json::{ self, Value };
+
+use super::registry::{ self, SlashCommand };
+
+/// The fields of a registered command Discord sends back that can be set when registering it,
+/// which are all that's compared against the registry.
+const REGISTERED_FIELDS: &[&str] = &[
+    "type",
+    "name",
+    "name_localizations",
+    "description",
+    "description_localizations",
+    "options",
+    "default_member_permissions",
+    "dm_permission",
+    "nsfw",
+];
+
+/// `Registration` is where the registry's commands are registered, set with
+/// `COMMAND_REGISTRATION`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Registration {
+    /// `global` - in every guild the bot is in and in DMs.
+    Global,
+
+    /// `guilds` - only in the guilds listed in `COMMAND_GUILDS`.
+    Guilds(Vec<GuildId>),
+
+    /// `dev` - only in `DEV_GUILD_ID`, to try commands out before they go global.
+    DevGuild(GuildId),
+}
+
+impl Registration {
+    /// Reads where commands are registered from `COMMAND_REGISTRATION`, which is `global`
+    /// unless set.
+    ///
+    /// # Errors
+    ///
+    /// Returns what's wrong if the mode is unknown or the guilds it needs aren't set.
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("COMMAND_REGISTRATION").as_deref().unwrap_or("global") {
+            "global" => Ok(Self::Global),
+            "guilds" => {
+                let guilds = env
+                    ::var("COMMAND_GUILDS")
+                    .map_err(|_| "`COMMAND_REGISTRATION=guilds` needs `COMMAND_GUILDS` set".to_owned())?;
+                let guilds = guilds
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(|id| id.parse().map_err(|_| format!("`{id}` in `COMMAND_GUILDS` isn't a guild id")))
+                    .collect::<Result<Vec<GuildId>, _>>()?;
+
+                if guilds.is_empty() {
+                    return Err("`COMMAND_GUILDS` doesn't list any guilds".to_owned());
+                }
+                Ok(Self::Guilds(guilds))
+            }
+            "dev" => {
+                let guild = env
+                    ::var("DEV_GUILD_ID")
+                    .map_err(|_| "`COMMAND_REGISTRATION=dev` needs `DEV_GUILD_ID` set".to_owned())?;
+                guild
+                    .trim()
+                    .parse()
+                    .map(Self::DevGuild)
+                    .map_err(|_| format!("`DEV_GUILD_ID` `{guild}` isn't a guild id"))
+            }
+            mode => {
+                Err(format!("unknown `COMMAND_REGISTRATION` `{mode}`, expected `global`, `guilds` or `dev`"))
+            }
+        }
+    }
+
+    /// The guilds commands are registered in, which is none of them when they're global.
+    pub fn guilds(&self) -> &[GuildId] {
+        match self {
+            Self::Global => &[],
+            Self::Guilds(guilds) => guilds,
+            Self::DevGuild(guild) => std::slice::from_ref(guild),
+        }
+    }
+
+    /// Whether commands are registered in `guild` itself, rather than globally or not at all.
+    pub fn includes(&self, guild: GuildId) -> bool {
+        self.guilds().contains(&guild)
+    }
+}
+
+impl fmt::Display for Registration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global => write!(f, "globally"),
+            Self::Guilds(guilds) => {
+                let guilds: Vec<String> = guilds.iter().map(ToString::to_string).collect();
+                write!(f, "in guilds {}", guilds.join(", "))
+            }
+            Self::DevGuild(guild) => write!(f, "in dev guild {guild}"),
+        }
+    }
+}
+
+/// `Scope` is one set of commands registered with Discord: the global ones or a guild's.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Scope {
+    Global,
+    Guild(GuildId),
+}
+
+impl Scope {
+    async fn fetch(self, http: &Http) -> serenity::Result<Vec<Command>> {
+        match self {
+            Self::Global => Command::get_global_commands_with_localizations(http).await,
+            Self::Guild(guild) => guild.get_commands_with_localizations(http).await,
+        }
+    }
+
+    async fn create(self, http: &Http, builder: CreateCommand) -> serenity::Result<Command> {
+        match self {
+            Self::Global => Command::create_global_command(http, builder).await,
+            Self::Guild(guild) => guild.create_command(http, builder).await,
+        }
+    }
+
+    async fn edit(self, http: &Http, id: CommandId, builder: CreateCommand) -> serenity::Result<Command> {
+        match self {
+            Self::Global => Command::edit_global_command(http, id, builder).await,
+            Self::Guild(guild) => guild.edit_command(http, id, builder).await,
+        }
+    }
+
+    async fn delete(self, http: &Http, id: CommandId) -> serenity::Result<()> {
+        match self {
+            Self::Global => Command::delete_global_command(http, id).await,
+            Self::Guild(guild) => guild.delete_command(http, id).await,
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global => write!(f, "global commands"),
+            Self::Guild(guild) => write!(f, "commands of guild {guild}"),
+        }
+    }
+}
+
+/// `SyncSummary` is what a sync changed.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    pub created: Vec<&'static str>,
+    pub edited: Vec<&'static str>,
+    pub deleted: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl SyncSummary {
+    /// Whether there were no commands on either side, so there's nothing worth logging.
+    pub const fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.edited.is_empty() && self.deleted.is_empty() && self.unchanged == 0
+    }
+}
+
+impl fmt::Display for SyncSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn names(f: &mut fmt::Formatter<'_>, what: &str, names: &[impl AsRef<str>]) -> fmt::Result {
+            write!(f, "{} {what}", names.len())?;
+            if !names.is_empty() {
+                let names: Vec<&str> = names.iter().map(AsRef::as_ref).collect();
+                write!(f, " ({})", names.join(", "))?;
+            }
+            Ok(())
+        }
+
+        names(f, "created", &self.created)?;
+        f.write_str(", ")?;
+        names(f, "edited", &self.edited)?;
+        f.write_str(", ")?;
+        names(f, "deleted", &self.deleted)?;
+        write!(f, ", {} unchanged", self.unchanged)
+    }
+}
+
+/// `SyncError` is the request that failed a sync, which stops at the first one.
+#[derive(Debug)]
+pub struct SyncError {
+    scope: Scope,
+    action: String,
+    why: String,
+}
+
+impl SyncError {
+    fn new(scope: Scope, action: impl Into<String>, why: &impl fmt::Display) -> Self {
+        Self {
+            scope,
+            action: action.into(),
+            why: why.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: can't {}: {}", self.scope, self.action, self.why)
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+/// Syncs every scope the bot is in to `registration`: the registry goes globally or into the
+/// guilds it lists, and is cleared out of everywhere else among `guilds`, where it may be left
+/// over from an earlier mode. Each scope that has commands gets its summary logged.
+///
+/// A scope that can't be cleared out, such as a guild that didn't give the bot
+/// `applications.commands`, is logged and skipped, the rest are synced regardless.
+///
+/// # Errors
+///
+/// Returns the first request that failed in a scope commands are registered in.
+pub async fn sync_all(http: &Http, registration: &Registration, guilds: &[GuildId]) -> Result<(), SyncError> {
+    let commands: Vec<&'static SlashCommand> = registry::commands().collect();
+
+    let mut scopes = vec![(Scope::Global, *registration == Registration::Global)];
+    scopes.extend(guilds.iter().map(|&guild| (Scope::Guild(guild), registration.includes(guild))));
+
+    let mut first_error = None;
+    for (scope, registered) in scopes {
+        let wanted: &[_] = if registered { &commands } else { &[] };
+        match sync(http, scope, wanted).await {
+            Ok(summary) if !summary.is_empty() => println!("Synced {scope}: {summary}"),
+            Ok(_) => {}
+            Err(why) if registered => {
+                first_error.get_or_insert(why);
+            }
+            Err(why) => println!("Couldn't clear out old commands, skipping it: {why}"),
+        }
+    }
+
+    for guild in registration.guilds().iter().filter(|guild| !guilds.contains(guild)) {
+        println!("Not registering commands in guild {guild}, the bot isn't in it");
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Makes the commands registered in `scope` match `commands`, only creating, editing and
+/// deleting the ones that differ.
+///
+/// # Errors
+///
+/// Returns the first request that failed.
+pub async fn sync(
+    http: &Http,
+    scope: Scope,
+    commands: &[&'static SlashCommand]
+) -> Result<SyncSummary, SyncError> {
+    let registered = scope
+        .fetch(http).await
+        .map_err(|why| SyncError::new(scope, "fetch the registered commands", &why))?;
+    let mut registered: HashMap<(CommandType, &str), &Command> = registered
+        .iter()
+        .map(|command| ((command.kind, command.name.as_str()), command))
+        .collect();
+
+    let mut summary = SyncSummary::default();
+    for command in commands {
+        let builder = (command.register)();
+
+        match registered.remove(&(command.kind, command.name)) {
+            None => {
+                scope
+                    .create(http, builder).await
+                    .map_err(|why| SyncError::new(scope, format!("create `{}`", command.name), &why))?;
+                summary.created.push(command.name);
+            }
+            Some(current) => {
+                if differs(scope, &builder, current)? {
+                    scope
+                        .edit(http, current.id, builder).await
+                        .map_err(|why| SyncError::new(scope, format!("edit `{}`", command.name), &why))?;
+                    summary.edited.push(command.name);
+                } else {
+                    summary.unchanged += 1;
+                }
+            }
+        }
+    }
+
+    let mut stale: Vec<&Command> = registered.into_values().collect();
+    stale.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    for command in stale {
+        scope
+            .delete(http, command.id).await
+            .map_err(|why| SyncError::new(scope, format!("delete `{}`", command.name), &why))?;
+        summary.deleted.push(command.name.clone());
+    }
+
+    Ok(summary)
+}
+
+/// Whether `current`, as Discord sent it back, is out of date with `builder`.
+fn differs(scope: Scope, builder: &CreateCommand, current: &Command) -> Result<bool, SyncError> {
+    let compare = |why: serenity::Error| SyncError::new(scope, format!("compare `{}`", current.name), &why);
+    let mut wanted = json::to_value(builder).map_err(compare)?;
+    let mut current = json::to_value(current).map_err(compare)?;
+    if let Value::Object(fields) = &mut current {
+        fields.retain(|field, _| REGISTERED_FIELDS.contains(&field.as_str()));
+    }
+
+    for command in [&mut wanted, &mut current] {
+        let Value::Object(fields) = command else {
+            continue;
+        };
+
+        // What Discord fills in when the builder leaves it out
+        if fields.get("type").is_none_or(Value::is_null) {
+            fields.insert("type".to_owned(), Value::from(u8::from(CommandType::ChatInput)));
+        }
+        match scope {
+            Scope::Global if fields.get("dm_permission").is_none_or(Value::is_null) => {
+                fields.insert("dm_permission".to_owned(), Value::Bool(true));
+            }
+            Scope::Global => {}
+            // Guild commands can't be used in DMs either way
+            Scope::Guild(_) => {
+                fields.remove("dm_permission");
+            }
+        }
+
+        prune(command);
+    }
+
+    Ok(wanted != current)
+}
+
+/// Drops the fields left at their defaults, so one the builder left out compares equal to the
+/// empty one Discord sends back.
+pub fn prune(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            fields.values_mut().for_each(prune);
+            fields.retain(|_, field| !is_default(field));
+        }
+        Value::Array(items) => items.iter_mut().for_each(prune),
+        _ => {}
+    }
+}
+
+fn is_default(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::String(string) => string.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        Value::Object(fields) => fields.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::{ Command, CommandOptionType, GuildId };
+    use serenity::builder::{ CreateCommand, CreateCommandOption };
+    use serenity::json::{ self, json, Value };
+
+    use super::{ differs, prune, Scope };
+
+    /// A command as Discord sends it back, with `fields` on top of the ones it always has.
+    fn registered(fields: &Value) -> Command {
+        let mut command = json!({
+            "id": "1",
+            "application_id": "2",
+            "version": "3",
+            "type": 1,
+            "name": "ping",
+            "description": "It Pings",
+        });
+        if let (Value::Object(command), Value::Object(fields)) = (&mut command, fields) {
+            command.extend(fields.clone());
+        }
+
+        json::from_value(command).expect("is a valid command")
+    }
+
+    fn ping() -> CreateCommand {
+        CreateCommand::new("ping").description("It Pings")
+    }
+
+    fn message_option() -> CreateCommandOption {
+        CreateCommandOption::new(CommandOptionType::String, "message", "What to answer with")
+    }
+
+    #[test]
+    fn defaults_and_nulls_compare_equal() {
+        let guild = Scope::Guild(GuildId::new(4));
+        let cases = [
+            ("type filled in", Scope::Global, ping(), json!({})),
+            (
+                "nulls and empties",
+                Scope::Global,
+                ping(),
+                json!({
+                    "name_localizations": null,
+                    "description_localizations": {},
+                    "options": [],
+                    "default_member_permissions": null,
+                    "nsfw": false,
+                }),
+            ),
+            ("dm_permission filled in", Scope::Global, ping(), json!({ "dm_permission": true })),
+            ("dm_permission set to its default", Scope::Global, ping().dm_permission(true), json!({})),
+            ("dm_permission in a guild", guild, ping().dm_permission(true), json!({ "dm_permission": false })),
+            (
+                "option flags left out",
+                Scope::Global,
+                ping().add_option(message_option()),
+                json!({
+                    "options": [{
+                        "type": 3,
+                        "name": "message",
+                        "description": "What to answer with",
+                        "required": false,
+                        "autocomplete": false,
+                        "choices": [],
+                    }],
+                }),
+            ),
+        ];
+
+        for (case, scope, builder, fields) in cases {
+            let differs = differs(scope, &builder, &registered(&fields)).expect("compares");
+            assert!(!differs, "{case}");
+        }
+    }
+
+    #[test]
+    fn changes_compare_different() {
+        let cases = [
+            ("description", ping().description("It Pongs"), json!({})),
+            ("option added", ping().add_option(message_option()), json!({})),
+            (
+                "option description",
+                ping().add_option(message_option().description("What to say")),
+                json!({ "options": [{ "type": 3, "name": "message", "description": "What to answer with" }] }),
+            ),
+            (
+                "option required",
+                ping().add_option(message_option().required(true)),
+                json!({ "options": [{ "type": 3, "name": "message", "description": "What to answer with" }] }),
+            ),
+            ("localization added", ping().description_localized("de", "Es pingt"), json!({})),
+            (
+                "localization changed",
+                ping().name_localized("de", "pingen"),
+                json!({ "name_localizations": { "de": "ping" } }),
+            ),
+            ("dm_permission", ping().dm_permission(false), json!({ "dm_permission": true })),
+            ("nsfw", ping().nsfw(true), json!({})),
+        ];
+
+        for (case, builder, fields) in cases {
+            let differs = differs(Scope::Global, &builder, &registered(&fields)).expect("compares");
+            assert!(differs, "{case}");
+        }
+    }
+
+    #[test]
+    fn prune_drops_defaults_at_every_level() {
+        let mut value = json!({
+            "name": "ping",
+            "description": "",
+            "nsfw": false,
+            "dm_permission": true,
+            "localizations": null,
+            "options": [{ "name": "message", "required": false, "choices": [], "min_length": 0 }],
+            "empty": { "nested": { "null": null } },
+        });
+        prune(&mut value);
+
+        assert_eq!(
+            value,
+            json!({
+                "name": "ping",
+                "dm_permission": true,
+                "options": [{ "name": "message", "min_length": 0 }],
+            })
+        );
+    }
+}
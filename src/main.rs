@@ -1,5 +1,6 @@
 use std::collections::{ HashMap, HashSet };
-use std::env;
+use std::env::{ self, VarError };
+use std::process;
 use std::sync::Arc;
 
 use dotenv::dotenv;
@@ -8,21 +9,29 @@ use event_handler::Handler;
 
 use serenity::gateway::ShardManager;
 use serenity::http::Http;
+use serenity::model::id::UserId;
 use serenity::prelude::*;
 use serenity::framework::standard::macros::group;
 use serenity::framework::standard::{
+    CommandGroup,
     StandardFramework,
     Configuration,
 };
 
 use songbird::{ SerenityInit, Songbird };
 
+mod cli;
 mod commands;
+mod cooldown;
 mod hooks;
+mod interactions;
 mod voice_handler;
 mod event_handler;
 
+use cli::{ Cli, USAGE };
+use cooldown::Cooldowns;
 use commands::owner::{ SLOW_MODE_COMMAND, LATENCY_COMMAND };
+use voice_handler::recording::Recordings;
 
 struct ShardManagerContainer;
 
@@ -30,6 +39,12 @@ impl TypeMapKey for ShardManagerContainer {
     type Value = Arc<ShardManager>;
 }
 
+pub struct BotOwners;
+
+impl TypeMapKey for BotOwners {
+    type Value = HashSet<UserId>;
+}
+
 pub struct CommandCounter;
 
 impl TypeMapKey for CommandCounter {
@@ -43,34 +58,70 @@ impl TypeMapKey for CommandCounter {
 #[commands(slow_mode, latency)]
 struct Owner;
 
-#[tokio::main]
-async fn main() {
-    dotenv().ok();
+/// What prefix commands start with.
+pub const PREFIX: &str = "!";
+
+/// Every group of prefix commands, shared by the framework and `/help`.
+pub static PREFIX_GROUPS: &[&CommandGroup] = &[&OWNER_GROUP];
+
+/// Reads the bot's token from `TOKEN`, falling back to the one compiled in from `token.txt`.
+fn token() -> String {
     let token_env_key = "TOKEN";
-    let songbird = Songbird::serenity();
 
     let builtin_token: Option<&'static str> =
         include_optional::include_str_optional!("../token.txt");
 
-    let token: String = match env::var(token_env_key) {
-        Ok(val) => {
-            println!("{}: {}", token_env_key, val);
-            val
-        }
-        Err(_e) => {
-            match builtin_token {
-                Some(token) => {
+    // Never echo the token, `commands push` and `commands clear` run in CI where it'd end up in
+    // the logs
+    match env::var(token_env_key) {
+        Ok(val) => val,
+        Err(why) => {
+            // `VarError::NotUnicode` displays the value it couldn't read
+            let why = match why {
+                VarError::NotPresent => "it isn't set",
+                VarError::NotUnicode(_) => "it isn't valid unicode",
+            };
+            builtin_token.map_or_else(
+                || panic!("Expected a token in the environment or at compile time"),
+                |token| {
                     println!(
-                        "Couldn't interpret {}: {} - Using compile time token instead.",
-                        token_env_key,
-                        _e
+                        "Couldn't interpret {token_env_key}: {why} - Using compile time token instead."
                     );
                     token.to_string()
                 }
-                None => panic!("Expected a token in the environment or at compile time"),
+            )
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+
+    if let Err(duplicates) = interactions::registry::check_duplicates() {
+        panic!("Slash commands registered more than once: {}", duplicates.join(", "));
+    }
+    if let Err(duplicates) = interactions::components::check_duplicates() {
+        panic!("Component or modal handlers registered more than once: {}", duplicates.join(", "));
+    }
+
+    match Cli::parse(env::args().skip(1)) {
+        Ok(None) => {}
+        Ok(Some(cli)) => {
+            if let Err(why) = cli.run(token).await {
+                eprintln!("{why}");
+                process::exit(1);
             }
+            return;
         }
-    };
+        Err(why) => {
+            eprintln!("{why}\n\n{USAGE}");
+            process::exit(2);
+        }
+    }
+
+    let token = token();
+    let songbird = Songbird::serenity();
 
     let http = Http::new(&token);
 
@@ -86,24 +137,29 @@ async fn main() {
 
             match http.get_current_user().await {
                 Ok(bot_id) => (owners, bot_id.id),
-                Err(why) => panic!("Could not access the bot id: {:?}", why),
+                Err(why) => panic!("Could not access the bot id: {why:?}"),
             }
         }
-        Err(why) => panic!("Could not access application info: {:?}", why),
+        Err(why) => panic!("Could not access application info: {why:?}"),
     };
 
-    let framework = StandardFramework::new()
-        .before(hooks::before)
-        .group(&OWNER_GROUP);
+    let framework = PREFIX_GROUPS
+        .iter()
+        .fold(StandardFramework::new().before(hooks::before), |framework, group| framework.group(group));
 
-    framework.configure(Configuration::new().prefix("!").on_mention(Some(bot_id)).owners(owners));
+    framework.configure(
+        Configuration::new().prefix(PREFIX).on_mention(Some(bot_id)).owners(owners.clone())
+    );
 
     let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
     let mut client = Client::builder(token, intents)
-        .event_handler(Handler)
+        .event_handler(Handler::from_env())
         .register_songbird_with(songbird)
         .framework(framework)
-        .type_map_insert::<CommandCounter>(HashMap::default()).await
+        .type_map_insert::<CommandCounter>(HashMap::default())
+        .type_map_insert::<BotOwners>(owners)
+        .type_map_insert::<Cooldowns>(Arc::default())
+        .type_map_insert::<Recordings>(Arc::default()).await
         .expect("Error creating client");
 
     {
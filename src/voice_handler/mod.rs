@@ -1,3 +1,9 @@
+pub mod flac;
+pub mod ogg_opus;
 pub mod receive_handler;
+pub mod recording;
+pub mod sink;
+pub mod tracks;
 pub mod wav_manager;
+pub mod writer;
 pub mod voice_channel;
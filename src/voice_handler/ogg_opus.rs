@@ -0,0 +1,261 @@
+use std::fs::File;
+use std::io::{ self, BufWriter, Write };
+use std::path::Path;
+
+use ogg::{ PacketWriteEndInfo, PacketWriter };
+use songbird::constants::SILENT_FRAME;
+use songbird::driver::opus::{ self, coder::Encoder, Application, Bitrate, Channels, SampleRate };
+
+use super::sink::RecordingSink;
+use super::tracks::TICK_SAMPLES;
+
+/// How long a tick is in the samples of one channel, which is how Ogg/Opus counts time.
+const TICK_LENGTH: u64 = TICK_SAMPLES as u64 / 2;
+
+/// How many packets go on a page before it's written out, a second's worth.
+const PAGE_PACKETS: u64 = 50;
+
+/// What decoded tracks are encoded at, about what Discord sends.
+const BITRATE: i32 = 64_000;
+
+/// The most bytes a packet can take, as libopus recommends sizing its output.
+const MAX_PACKET_SIZE: usize = 4000;
+
+/// The serial number of the only stream in the file.
+const SERIAL: u32 = 1;
+
+/// `OggOpusSink` streams a track into an Ogg/Opus file, encoding decoded tracks and storing
+/// Discord's own packets as they are.
+pub struct OggOpusSink {
+    file: PacketWriter<BufWriter<File>>,
+    encoder: Encoder,
+
+    /// How many samples at the start players skip, the encoder's lookahead.
+    pre_skip: u64,
+
+    /// How far the packets written reach, in samples of one channel.
+    position: u64,
+
+    /// How many ticks were written, which is where the packets should reach.
+    ticks: u64,
+
+    packets: u64,
+    size: u64,
+}
+
+impl OggOpusSink {
+    /// Creates a new Ogg/Opus file at `path` to stream packets into.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut encoder = Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Voip)
+            .map_err(io::Error::other)?;
+        encoder.set_bitrate(Bitrate::BitsPerSecond(BITRATE)).map_err(io::Error::other)?;
+        let pre_skip = encoder.lookahead().map_err(io::Error::other)?;
+
+        let mut head = b"OpusHead".to_vec();
+        head.push(1);
+        head.push(2);
+        head.extend_from_slice(&u16::try_from(pre_skip).unwrap_or_default().to_le_bytes());
+        head.extend_from_slice(&48000u32.to_le_bytes());
+        // No output gain, and a plain stereo channel mapping
+        head.extend_from_slice(&[0, 0, 0]);
+
+        let vendor = env!("CARGO_PKG_NAME");
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&u32::try_from(vendor.len()).unwrap_or_default().to_le_bytes());
+        tags.extend_from_slice(vendor.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut file = PacketWriter::new(BufWriter::new(File::create(path)?));
+        // Both headers go on pages of their own
+        file.write_packet(Box::from(head.as_slice()), SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+        file.write_packet(Box::from(tags.as_slice()), SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+        Ok(Self {
+            file,
+            encoder,
+            pre_skip: u64::from(pre_skip),
+            position: 0,
+            ticks: 0,
+            packets: 0,
+            size: (head.len() + tags.len()) as u64,
+        })
+    }
+
+    /// Writes a packet reaching `length` samples further.
+    fn push(&mut self, packet: &[u8], length: u64) -> io::Result<()> {
+        self.position += length;
+        self.packets += 1;
+        self.size += packet.len() as u64;
+
+        let end = if self.packets.is_multiple_of(PAGE_PACKETS) {
+            PacketWriteEndInfo::EndPage
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        self.file.write_packet(Box::from(packet), SERIAL, end, self.pre_skip + self.position)
+    }
+}
+
+impl RecordingSink for OggOpusSink {
+    fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        let mut packet = [0; MAX_PACKET_SIZE];
+        let len = self.encoder.encode(samples, &mut packet).map_err(io::Error::other)?;
+
+        self.ticks += 1;
+        self.push(&packet[..len], TICK_LENGTH)
+    }
+
+    fn write_packet(&mut self, packet: &[u8]) -> io::Result<()> {
+        // Some clients send more than a tick in a packet, which is counted whole, and the silence
+        // padding the ticks it covers is skipped by `write_silence`
+        let length = opus::packet::Packet
+            ::try_from(packet)
+            .and_then(|packet| opus::packet::nb_samples(packet, SampleRate::Hz48000))
+            .map_or(TICK_LENGTH, |samples| samples as u64);
+
+        self.ticks += 1;
+        self.push(packet, length)
+    }
+
+    fn write_silence(&mut self, ticks: usize) -> io::Result<()> {
+        for _ in 0..ticks {
+            self.ticks += 1;
+            if self.position < self.ticks * TICK_LENGTH {
+                self.push(&SILENT_FRAME, TICK_LENGTH)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Only writes out whole pages, so up to a second is lost if the bot crashes.
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.inner_mut().flush()
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        // The stream has to end on a packet, a silent one that's trimmed off by ending the stream
+        // where the packets before it did
+        let end = self.pre_skip + self.position;
+        self.file.write_packet(Box::from(SILENT_FRAME), SERIAL, PacketWriteEndInfo::EndStream, end)?;
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{ self, File };
+    use std::path::PathBuf;
+
+    use ogg::{ Packet, PacketReader };
+    use songbird::driver::opus::{ coder::Encoder, Application, Channels, SampleRate };
+
+    use super::{ OggOpusSink, MAX_PACKET_SIZE, PAGE_PACKETS, TICK_LENGTH };
+    use crate::voice_handler::sink::RecordingSink;
+    use crate::voice_handler::tracks::TICK_SAMPLES;
+
+    /// A file for a test to write to, removed again when dropped.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            Self(env::temp_dir().join(format!("{}_{}_{name}.opus", env!("CARGO_PKG_NAME"), std::process::id())))
+        }
+
+        fn packets(&self) -> Vec<Packet> {
+            let mut reader = PacketReader::new(File::open(&self.0).expect("was written"));
+            std::iter::from_fn(|| reader.read_packet().expect("is an Ogg file")).collect()
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn headers_go_on_pages_of_their_own() {
+        let scratch = Scratch::new("headers");
+        let sink = OggOpusSink::create(&scratch.0).expect("can create");
+        let pre_skip = sink.pre_skip;
+        Box::new(sink).finish().expect("can finish");
+
+        let packets = scratch.packets();
+        let [head, tags, end] = packets.as_slice() else {
+            panic!("expected two headers and the end of the stream, got {} packets", packets.len());
+        };
+
+        assert_eq!(&head.data[..8], b"OpusHead");
+        assert_eq!(head.data[8..10], [1, 2]);
+        assert_eq!(u64::from(u16::from_le_bytes([head.data[10], head.data[11]])), pre_skip);
+        assert_eq!(head.data[12..16], 48000u32.to_le_bytes());
+        assert_eq!(head.data[16..], [0, 0, 0]);
+        assert!(head.first_in_page() && head.last_in_page() && head.absgp_page() == 0);
+
+        let vendor = env!("CARGO_PKG_NAME");
+        assert_eq!(&tags.data[..8], b"OpusTags");
+        assert_eq!(tags.data[8..12], u32::try_from(vendor.len()).expect("fits").to_le_bytes());
+        assert_eq!(&tags.data[12..12 + vendor.len()], vendor.as_bytes());
+        assert_eq!(tags.data[12 + vendor.len()..], 0u32.to_le_bytes());
+        assert!(tags.first_in_page() && tags.last_in_page() && tags.absgp_page() == 0);
+
+        // Nothing was recorded, so the stream ends where it starts
+        assert!(end.last_in_stream());
+        assert_eq!(end.absgp_page(), pre_skip);
+    }
+
+    #[test]
+    fn silent_gaps_are_padded_and_counted() {
+        let scratch = Scratch::new("gap");
+        let mut sink = OggOpusSink::create(&scratch.0).expect("can create");
+        let pre_skip = sink.pre_skip;
+
+        let gap = usize::try_from(PAGE_PACKETS).expect("fits") + 10;
+        sink.write(&[0; TICK_SAMPLES]).expect("can write");
+        sink.write_silence(gap).expect("can write");
+        sink.write(&[0; TICK_SAMPLES]).expect("can write");
+        Box::new(sink).finish().expect("can finish");
+
+        let packets = scratch.packets();
+        let audio = &packets[2..packets.len() - 1];
+        assert_eq!(audio.len(), gap + 2);
+
+        // A page ends a second in, partway through the gap, and the last one where the audio does
+        let pages: Vec<u64> = packets[2..]
+            .iter()
+            .filter(|packet| packet.last_in_page())
+            .map(Packet::absgp_page)
+            .collect();
+        let ticks = u64::try_from(gap).expect("fits") + 2;
+        assert_eq!(pages, [pre_skip + PAGE_PACKETS * TICK_LENGTH, pre_skip + ticks * TICK_LENGTH]);
+    }
+
+    #[test]
+    fn long_packets_cover_the_silence_after_them() {
+        let scratch = Scratch::new("long_packet");
+        let mut sink = OggOpusSink::create(&scratch.0).expect("can create");
+        let pre_skip = sink.pre_skip;
+
+        // 40 ms, two ticks' worth
+        let encoder = Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Voip)
+            .expect("can create an encoder");
+        let mut packet = [0; MAX_PACKET_SIZE];
+        let len = encoder.encode(&[0; TICK_SAMPLES * 2], &mut packet).expect("can encode");
+
+        sink.write_packet(&packet[..len]).expect("can write");
+        // The first tick of silence is in the packet already
+        sink.write_silence(2).expect("can write");
+        Box::new(sink).finish().expect("can finish");
+
+        let packets = scratch.packets();
+        assert_eq!(packets.len(), 2 + 2 + 1);
+        assert_eq!(packets[2].data, &packet[..len]);
+        assert_eq!(packets.last().map(Packet::absgp_page), Some(pre_skip + 3 * TICK_LENGTH));
+    }
+}
@@ -1,67 +1,101 @@
+use std::io;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
-use std::time::Instant;
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
 use songbird::events::context_data::VoiceTick;
-use tokio::sync::Mutex;
+use tokio::sync::{ mpsc, Mutex };
+use tokio::sync::mpsc::error::TrySendError;
 
+use chrono::Utc;
 use serenity::async_trait;
+use serenity::model::id::GuildId;
 use songbird::Event;
 use songbird::EventContext;
 use songbird::EventHandler as VoiceEventHandler;
 
-use super::wav_manager;
-
-static SAMPLE_RATE: u32 = 48000;
+use super::sink::Format;
+use super::tracks::Ssrcs;
+use super::writer::{ self, Frame, WriteError, WriterTask };
 
+/// `ReceiveHandler` records a guild's call while it's attached to it. Clones share the same
+/// recording.
 #[derive(Clone)]
 pub struct ReceiveHandler {
-    last_voice_packet_time: Arc<Mutex<Instant>>,
-    pub voice_buffer: Arc<Mutex<Vec<i16>>>,
+    guild: GuildId,
+
+    /// Where each tick is sent to be written to disk, until the recording is over.
+    frames: Arc<Mutex<Option<mpsc::Sender<Frame>>>>,
+
+    /// The task writing the recording to disk, until it's waited on.
+    writer: Arc<Mutex<Option<WriterTask>>>,
+
+    /// Set once the recording is over, after which the handler detaches itself from the call.
+    stopped: Arc<AtomicBool>,
+
+    /// How many ticks were dropped because the writer fell behind.
+    dropped: Arc<AtomicUsize>,
 }
 
 impl ReceiveHandler {
-    pub fn new() -> Self {
-        let new_voice_buffer: Arc<Mutex<Vec<i16>>> = Arc::new(Mutex::new(Vec::new()));
+    /// Starts recording a track for each speaker in `guild` as `format`, and everyone mixed into
+    /// one more with `mixdown`.
+    pub fn new(guild: GuildId, mixdown: bool, format: Format, ssrcs: Ssrcs) -> Self {
+        let (frames, writer) = writer::spawn(guild, Utc::now(), ssrcs, mixdown, format);
 
         Self {
-            last_voice_packet_time: Arc::new(Mutex::new(Instant::now())),
-            voice_buffer: new_voice_buffer,
+            guild,
+            frames: Arc::new(Mutex::new(Some(frames))),
+            writer: Arc::new(Mutex::new(Some(writer))),
+            stopped: Arc::new(AtomicBool::new(false)),
+            dropped: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    async fn handle_voice_tick(&self, voice_tick: &VoiceTick) {
-        if voice_tick.speaking.len() <= 0 {
-            return;
+    /// Whether the recording is over, either stopped or cut off by a disconnect.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    /// How many ticks are missing from the recording because the disk couldn't keep up.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stops recording and waits for the files to be finished, returning where they are. Only
+    /// the first call saves anything, later ones return `None`.
+    pub async fn finish(&self) -> Option<Result<Vec<PathBuf>, WriteError>> {
+        if self.stopped.swap(true, Ordering::AcqRel) {
+            return None;
         }
-        let mut last_voice_tick_time = self.last_voice_packet_time.lock().await;
 
-        let now = Instant::now();
-        let gap_duration = now.duration_since(*last_voice_tick_time);
+        // Dropping the last sender lets the writer run out of frames and finish
+        self.frames.lock().await.take();
+        let writer = self.writer.lock().await.take()?;
 
-        let mut buffer = self.voice_buffer.lock().await;
+        Some(writer.await.unwrap_or_else(|why| Err(WriteError { why: io::Error::other(why), saved: Vec::new() })))
+    }
 
-        if gap_duration > Duration::from_millis(100) {
-            let silence_samples: usize = (gap_duration.as_secs_f32() *
-                (SAMPLE_RATE as f32)) as usize; // Calculate as usize
-            buffer.extend(vec![0i16; silence_samples]); // Extend buffer with i16 zeros
-            println!("leaving gap of {gap_duration:?}");
-        }
+    async fn handle_voice_tick(&self, voice_tick: &VoiceTick) {
+        let Some(frames) = self.frames.lock().await.clone() else {
+            return;
+        };
 
-        // Loop through each speaking user
-        for (_user_id, voice_data) in &voice_tick.speaking {
-            // If there is decoded voice data for the user, append it to the buffer
-            if let Some(decoded_voice) = &voice_data.decoded_voice {
-                buffer.extend(decoded_voice);
-            }
+        // Every tick is sent, silent or not, to keep the tracks lined up. Waiting for room would
+        // hold up songbird's events for the whole call, so ticks the writer has no room for are
+        // dropped and counted instead. If the writer failed, `finish` says why
+        if let Err(TrySendError::Full(_)) = frames.try_send(Frame::from(voice_tick)) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
         }
-
-        *last_voice_tick_time = now;
     }
 }
 
 #[async_trait]
 impl VoiceEventHandler for ReceiveHandler {
     async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if self.is_stopped() {
+            return Some(Event::Cancel);
+        }
+
         match ctx {
             EventContext::VoiceTick(packet) => {
                 self.handle_voice_tick(packet).await;
@@ -73,39 +107,24 @@ impl VoiceEventHandler for ReceiveHandler {
             //     println!("Received Rtcp packet");
             // }
             EventContext::DriverDisconnect(_event) => {
-                let buffer = self.voice_buffer.lock().await;
-                wav_manager::write(buffer)
+                let guild = self.guild;
+                match self.finish().await {
+                    Some(Ok(paths)) => {
+                        println!(
+                            "Saved the recording cut off in {guild} to {paths:?}, {} tick(s) dropped",
+                            self.dropped()
+                        );
+                    }
+                    Some(Err(why)) => println!("Failed to save the recording cut off in {guild}: {why}"),
+                    None => {}
+                }
             }
             _ => {
                 // We do not care about any other events in this example.
-                println!("Received event {:?}", ctx);
+                println!("Received event {ctx:?}");
             }
         }
 
         None
     }
 }
-
-pub struct ArcMutexReceiveHandler(Arc<Mutex<ReceiveHandler>>);
-
-impl ArcMutexReceiveHandler {
-    pub fn new(handler: Arc<Mutex<ReceiveHandler>>) -> Self {
-        Self(handler)
-    }
-
-    pub fn clone(&self) -> Self {
-        Self(self.0.clone())
-    }
-}
-
-#[async_trait]
-impl VoiceEventHandler for ArcMutexReceiveHandler {
-    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
-        let result;
-        {
-            let handler = self.0.lock().await;
-            result = handler.act(ctx).await;
-        }
-        result
-    }
-}
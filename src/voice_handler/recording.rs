@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{ DateTime, Utc };
+use serenity::client::Context;
+use serenity::model::id::{ GuildId, UserId };
+use serenity::prelude::TypeMapKey;
+use songbird::driver::{ DecodeMode, Driver };
+use songbird::{ Call, CoreEvent, Event };
+use tokio::sync::Mutex;
+
+use crate::interactions::error::CommandError;
+use crate::voice_handler::receive_handler::ReceiveHandler;
+use crate::voice_handler::sink::Format;
+use crate::voice_handler::tracks::{ Ssrcs, TICK_MILLIS };
+
+/// `Session` is a recording running in a guild's call.
+#[derive(Clone)]
+pub struct Session {
+    pub started_by: UserId,
+    pub started_at: DateTime<Utc>,
+    pub format: Format,
+    handler: ReceiveHandler,
+}
+
+impl Session {
+    /// Who started the session and when, as Discord renders it.
+    pub fn describe(&self) -> String {
+        format!("started by <@{}> <t:{}:R>", self.started_by, self.started_at.timestamp())
+    }
+
+    /// What's missing from the recording because the disk couldn't keep up, if anything.
+    pub fn describe_dropped(&self) -> Option<String> {
+        let ticks = self.handler.dropped();
+        (ticks > 0).then(|| {
+            format!("{} ms of audio were dropped, the bot's disk couldn't keep up", ticks * TICK_MILLIS)
+        })
+    }
+}
+
+/// `Recordings` tracks the recording session of each guild, who's who in the calls the bot is
+/// in, and the format each guild records in, shared through the `TypeMap`.
+#[derive(Default)]
+pub struct Recordings {
+    sessions: Mutex<HashMap<GuildId, Session>>,
+    ssrcs: Mutex<HashMap<GuildId, Ssrcs>>,
+    formats: Mutex<HashMap<GuildId, Format>>,
+}
+
+impl TypeMapKey for Recordings {
+    type Value = Arc<Self>;
+}
+
+async fn recordings(ctx: &Context) -> Arc<Recordings> {
+    ctx.data
+        .read().await
+        .get::<Recordings>()
+        .cloned()
+        .expect("Recordings placed in at initialization.")
+}
+
+/// Starts learning who's who in the call just joined in `guild`, unless the bot was already in
+/// one there.
+pub async fn track_speakers(ctx: &Context, guild: GuildId, call: &Arc<Mutex<Call>>) {
+    let recordings = recordings(ctx).await;
+    let mut ssrcs = recordings.ssrcs.lock().await;
+    if ssrcs.contains_key(&guild) {
+        return;
+    }
+
+    let tracker = Ssrcs::default();
+    call.lock().await.add_global_event(Event::Core(CoreEvent::SpeakingStateUpdate), tracker.clone());
+    ssrcs.insert(guild, tracker);
+}
+
+/// Forgets who's who in the call left in `guild`.
+pub async fn forget_speakers(ctx: &Context, guild: GuildId) {
+    recordings(ctx).await.ssrcs.lock().await.remove(&guild);
+}
+
+/// The format `guild` records in unless `/record_voice start` says otherwise, the one it set or
+/// `RECORDING_FORMAT`.
+pub async fn format(ctx: &Context, guild: GuildId) -> Format {
+    recordings(ctx).await.formats.lock().await.get(&guild).copied().unwrap_or_else(Format::from_env)
+}
+
+/// Makes `guild` record in `format` from now on, until the bot restarts.
+pub async fn set_format(ctx: &Context, guild: GuildId, format: Format) {
+    recordings(ctx).await.formats.lock().await.insert(guild, format);
+}
+
+/// Forgets the format set by `guild`.
+pub async fn forget_format(ctx: &Context, guild: GuildId) {
+    recordings(ctx).await.formats.lock().await.remove(&guild);
+}
+
+/// The session recording in `guild`, if there is one.
+pub async fn session(ctx: &Context, guild: GuildId) -> Option<Session> {
+    let recordings = recordings(ctx).await;
+    let mut sessions = recordings.sessions.lock().await;
+    drop_stopped(&mut sessions);
+    sessions.get(&guild).cloned()
+}
+
+/// Drops the sessions cut off by a disconnect, which their handler already saved.
+fn drop_stopped(sessions: &mut HashMap<GuildId, Session>) {
+    sessions.retain(|_, session| !session.handler.is_stopped());
+}
+
+/// Starts recording the call the bot is in in `guild` as `format`, or the guild's format if
+/// `None`, with everyone mixed into one more file if `mixdown` is set.
+///
+/// Ogg/Opus recordings without a mixdown keep Discord's packets as they are, so the call only
+/// decrypts what it receives until the recording stops.
+///
+/// # Errors
+///
+/// Returns a user error if the bot isn't in a call there or it's already being recorded.
+pub async fn start(
+    ctx: &Context,
+    guild: GuildId,
+    user: UserId,
+    mixdown: bool,
+    format: Option<Format>
+) -> Result<Session, CommandError> {
+    let manager = songbird
+        ::get(ctx).await
+        .expect("Songbird Voice client placed in at initialization.");
+    let Some(call) = manager.get(guild) else {
+        return Err(CommandError::user("I'm not in a voice channel, use /join_channel first"));
+    };
+
+    let format = match format {
+        Some(format) => format,
+        None => self::format(ctx, guild).await,
+    };
+    let passthrough = format == Format::Opus && !mixdown;
+
+    let recordings = recordings(ctx).await;
+    let ssrcs = recordings.ssrcs.lock().await.get(&guild).cloned().unwrap_or_default();
+
+    // Held until the session is in, so two starts at once can't both attach a handler
+    let mut sessions = recordings.sessions.lock().await;
+    drop_stopped(&mut sessions);
+    if let Some(session) = sessions.get(&guild) {
+        return Err(CommandError::user(format!("Already recording, {}", session.describe())));
+    }
+
+    let handler = ReceiveHandler::new(guild, mixdown, format, ssrcs);
+    {
+        let mut call = call.lock().await;
+        set_decode_mode(&mut call, if passthrough { DecodeMode::Decrypt } else { DecodeMode::Decode });
+        call.add_global_event(Event::Core(CoreEvent::VoiceTick), handler.clone());
+        call.add_global_event(Event::Core(CoreEvent::DriverDisconnect), handler.clone());
+    }
+
+    let session = Session {
+        started_by: user,
+        started_at: Utc::now(),
+        format,
+        handler,
+    };
+    sessions.insert(guild, session.clone());
+    drop(sessions);
+
+    Ok(session)
+}
+
+/// Stops the recording in `guild` and saves it, without leaving the call. Returns the session
+/// and the files it was saved to, or `None` if nothing was being recorded.
+///
+/// # Errors
+///
+/// Returns an internal error if the recording couldn't be saved to the end, naming the files
+/// saved until then.
+pub async fn stop(ctx: &Context, guild: GuildId) -> Result<Option<(Session, Vec<PathBuf>)>, CommandError> {
+    let session = recordings(ctx).await.sessions.lock().await.remove(&guild);
+    let Some(session) = session else {
+        return Ok(None);
+    };
+
+    let manager = songbird
+        ::get(ctx).await
+        .expect("Songbird Voice client placed in at initialization.");
+    if let Some(call) = manager.get(guild) {
+        set_decode_mode(&mut *call.lock().await, DecodeMode::Decode);
+    }
+
+    match session.handler.finish().await {
+        Some(Ok(paths)) => Ok(Some((session, paths))),
+        Some(Err(why)) => Err(CommandError::internal(format!("can't save the recording: {why}"))),
+        // Cut off by a disconnect, which saved it already
+        None => Ok(None),
+    }
+}
+
+/// Changes what the call does with the audio it receives, right away and after reconnecting.
+fn set_decode_mode(call: &mut Call, mode: DecodeMode) {
+    let config = call.config().clone().decode_mode(mode);
+    call.set_config(config.clone());
+    Driver::set_config(call, config);
+}
@@ -0,0 +1,159 @@
+use std::{ env, fmt, io, path::{ Path, PathBuf } };
+use std::fs::File;
+use std::io::BufWriter;
+
+use chrono::{ DateTime, Utc };
+use greys_macros::SlashChoice;
+use hound::WavWriter;
+use serenity::model::id::GuildId;
+
+use super::flac::FlacSink;
+use super::ogg_opus::OggOpusSink;
+use super::tracks::TICK_SAMPLES;
+use super::wav_manager;
+
+/// `RecordingSink` is a file a track of a recording is streamed into, one tick at a time.
+pub trait RecordingSink: Send {
+    /// Writes a tick of decoded samples, exactly `TICK_SAMPLES` of them.
+    fn write(&mut self, samples: &[i16]) -> io::Result<()>;
+
+    /// Writes a tick as the Opus packet it arrived in, which only formats storing Opus can do
+    /// without decoding it first.
+    fn write_packet(&mut self, _packet: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "only Ogg/Opus can store undecoded audio"))
+    }
+
+    /// Writes `ticks` ticks of silence.
+    fn write_silence(&mut self, ticks: usize) -> io::Result<()>;
+
+    /// Makes everything written so far readable, even if the bot crashes afterwards.
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// Roughly how big the file is so far, in bytes.
+    fn size(&self) -> u64;
+
+    /// Finishes the file.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// `Format` is what a recording is saved as.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, SlashChoice)]
+pub enum Format {
+    /// Uncompressed, around 11 MB a minute.
+    #[default]
+    #[description("WAV, uncompressed")]
+    Wav,
+
+    /// Lossless, usually around half the size of WAV for speech.
+    #[description("FLAC, lossless")]
+    Flac,
+
+    /// Lossy but tiny, around 0.5 MB a minute. Speakers are saved as Discord sent them unless
+    /// they're also mixed down.
+    #[description("Ogg/Opus, smallest")]
+    Opus,
+}
+
+impl Format {
+    /// The format recordings are saved as unless a guild or `/record_voice` says otherwise,
+    /// `RECORDING_FORMAT` or WAV.
+    pub fn from_env() -> Self {
+        env
+            ::var("RECORDING_FORMAT")
+            .ok()
+            .and_then(|val| Self::parse(&val))
+            .unwrap_or_default()
+    }
+
+    fn parse(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "wav" => Some(Self::Wav),
+            "flac" => Some(Self::Flac),
+            "opus" | "ogg" => Some(Self::Opus),
+            _ => None,
+        }
+    }
+
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+            Self::Opus => "opus",
+        }
+    }
+
+    /// The biggest a file can get, WAV's sizes being 32-bit. Leaves room for the header and the
+    /// tick written after a part's size was last checked.
+    pub const fn max_size(self) -> u64 {
+        match self {
+            Self::Wav => u32::MAX as u64 - 1024 * 1024,
+            Self::Flac | Self::Opus => u64::MAX,
+        }
+    }
+
+    /// Creates a new file at `path` to stream a track into.
+    pub fn create(self, path: &Path) -> io::Result<Box<dyn RecordingSink>> {
+        Ok(match self {
+            Self::Wav => Box::new(WavSink(wav_manager::create(path).map_err(into_io)?)),
+            Self::Flac => Box::new(FlacSink::create(path, sample_rate())?),
+            Self::Opus => Box::new(OggOpusSink::create(path)?),
+        })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wav => write!(f, "WAV"),
+            Self::Flac => write!(f, "FLAC"),
+            Self::Opus => write!(f, "Ogg/Opus"),
+        }
+    }
+}
+
+/// Where the `track` of the recording started at `started` in `guild` is saved as `format`.
+pub fn path(guild: GuildId, started: DateTime<Utc>, track: &str, format: Format) -> PathBuf {
+    PathBuf::from(
+        format!("recording_{guild}_{}_{track}.{}", started.format("%Y%m%d%H%M%S"), format.extension())
+    )
+}
+
+/// The sample rate lossless recordings are saved with, `SAMPLE_RATE` or 48 kHz.
+pub fn sample_rate() -> u32 {
+    let default_sample_rate = 48000;
+    env
+        ::var("SAMPLE_RATE")
+        .map_or(default_sample_rate, |val| val.parse().unwrap_or(default_sample_rate))
+}
+
+/// `WavSink` streams a track into a 16-bit stereo WAV file.
+struct WavSink(WavWriter<BufWriter<File>>);
+
+impl RecordingSink for WavSink {
+    fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        samples.iter().try_for_each(|&sample| self.0.write_sample(sample)).map_err(into_io)
+    }
+
+    fn write_silence(&mut self, ticks: usize) -> io::Result<()> {
+        (0..ticks * TICK_SAMPLES).try_for_each(|_| self.0.write_sample(0i16)).map_err(into_io)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush().map_err(into_io)
+    }
+
+    fn size(&self) -> u64 {
+        u64::from(self.0.len()) * 2
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.0.finalize().map_err(into_io)
+    }
+}
+
+fn into_io(why: hound::Error) -> io::Error {
+    match why {
+        hound::Error::IoError(why) => why,
+        why => io::Error::other(why),
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{ Arc, Mutex, PoisonError };
+
+use serenity::async_trait;
+use serenity::model::id::UserId;
+use songbird::{ Event, EventContext, EventHandler as VoiceEventHandler };
+
+/// How many samples a `VoiceTick` holds for each speaker: 20 ms of 48 kHz stereo.
+pub const TICK_SAMPLES: usize = 48000 / 50 * 2;
+
+/// How long a `VoiceTick` lasts.
+pub const TICK_MILLIS: usize = 20;
+
+/// How loud a mixed sample gets before it's compressed, three quarters of full scale.
+const KNEE: i32 = 24576;
+
+/// How much louder than `KNEE` a compressed sample can still get.
+const HEADROOM: i32 = 32767 - KNEE;
+
+/// `Speaker` is who a track was recorded from.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Speaker {
+    User(UserId),
+
+    /// Someone Discord hasn't said the user of yet, by the SSRC their audio comes in under.
+    Unknown(u32),
+}
+
+impl fmt::Display for Speaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User(user) => write!(f, "user_{user}"),
+            Self::Unknown(ssrc) => write!(f, "ssrc_{ssrc}"),
+        }
+    }
+}
+
+/// `Ssrcs` learns which user is behind each SSRC of a call from its `SpeakingStateUpdate`s.
+///
+/// Discord only says so once per user per connection, so it stays attached for as long as the
+/// bot is in the call rather than just while recording.
+#[derive(Clone, Default)]
+pub struct Ssrcs(Arc<Mutex<HashMap<u32, UserId>>>);
+
+impl Ssrcs {
+    /// The user speaking under `ssrc`, if Discord has said yet.
+    pub fn user(&self, ssrc: u32) -> Option<UserId> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).get(&ssrc).copied()
+    }
+}
+
+#[async_trait]
+impl VoiceEventHandler for Ssrcs {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::SpeakingStateUpdate(speaking) = ctx {
+            if let Some(user) = speaking.user_id {
+                self.0.lock().unwrap_or_else(PoisonError::into_inner).insert(speaking.ssrc, UserId::new(user.0));
+            }
+        }
+
+        None
+    }
+}
+
+/// Mixes the samples of everyone speaking in a tick into exactly one tick's worth of samples,
+/// which is silence if nobody is.
+pub fn mix<'a>(speaking: impl IntoIterator<Item = &'a [i16]>) -> Vec<i16> {
+    let mut sums = vec![0i32; TICK_SAMPLES];
+
+    for samples in speaking {
+        for (sum, &sample) in sums.iter_mut().zip(samples) {
+            *sum += i32::from(sample);
+        }
+    }
+
+    sums.into_iter().map(soft_clip).collect()
+}
+
+/// Brings a sum of samples back into range, leaving everything up to `KNEE` as it is and
+/// compressing what's louder smoothly towards full scale instead of clipping it flat.
+fn soft_clip(sum: i32) -> i16 {
+    let magnitude = sum.abs();
+    if magnitude <= KNEE {
+        return i16::try_from(sum).unwrap_or_default();
+    }
+
+    let over = i64::from(magnitude - KNEE);
+    let compressed = over * i64::from(HEADROOM) / (over + i64::from(HEADROOM));
+    let clipped = KNEE + i32::try_from(compressed).unwrap_or(HEADROOM);
+
+    i16::try_from(clipped * sum.signum()).unwrap_or(i16::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::model::id::UserId;
+
+    use super::{ mix, soft_clip, Ssrcs, KNEE, TICK_SAMPLES };
+
+    #[test]
+    fn silence_mixes_to_silence() {
+        assert_eq!(mix([]), vec![0; TICK_SAMPLES]);
+
+        let silent = [0; TICK_SAMPLES];
+        assert_eq!(mix([silent.as_slice(), silent.as_slice()]), vec![0; TICK_SAMPLES]);
+    }
+
+    #[test]
+    fn one_speaker_below_the_knee_passes_through() {
+        let knee = i16::try_from(KNEE).expect("the knee is a sample");
+        let speaker: Vec<i16> = (0..TICK_SAMPLES)
+            .map(|index| [knee, -knee, 0, 1, -1, 1000][index % 6])
+            .collect();
+
+        assert_eq!(mix([speaker.as_slice()]), speaker);
+    }
+
+    #[test]
+    fn loud_speakers_bend_instead_of_wrapping() {
+        let loud = [i16::MAX; TICK_SAMPLES];
+        let mixed = mix([loud.as_slice(), loud.as_slice()]);
+        assert!(mixed.iter().all(|&sample| i32::from(sample) > KNEE));
+
+        let quiet = [i16::MIN; TICK_SAMPLES];
+        let mixed = mix([quiet.as_slice(), quiet.as_slice()]);
+        assert!(mixed.iter().all(|&sample| i32::from(sample) < -KNEE));
+
+        // Every sum two speakers can reach, louder ones never coming out quieter or jumping
+        let mut last = soft_clip(2 * i32::from(i16::MIN));
+        for sum in 2 * i32::from(i16::MIN) + 1..=2 * i32::from(i16::MAX) {
+            let clipped = soft_clip(sum);
+            assert!(clipped >= last, "{sum}");
+            assert!(i32::from(clipped) - i32::from(last) <= 1, "{sum}");
+            last = clipped;
+        }
+    }
+
+    #[test]
+    fn missing_samples_are_silence() {
+        let speaker = [100; TICK_SAMPLES];
+        let cut_off = [200; TICK_SAMPLES / 2];
+
+        let mixed = mix([speaker.as_slice(), cut_off.as_slice()]);
+        assert!(mixed[..TICK_SAMPLES / 2].iter().all(|&sample| sample == 300));
+        assert!(mixed[TICK_SAMPLES / 2..].iter().all(|&sample| sample == 100));
+    }
+
+    #[test]
+    fn ssrcs_only_know_who_they_were_told_about() {
+        let ssrcs = Ssrcs::default();
+        assert_eq!(ssrcs.user(1), None);
+
+        // The clone attached to the call shares what it learns with the recordings
+        let attached = ssrcs.clone();
+        attached.0.lock().expect("not poisoned").insert(1, UserId::new(10));
+        assert_eq!(ssrcs.user(1), Some(UserId::new(10)));
+        assert_eq!(ssrcs.user(2), None);
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::{ HashMap, HashSet };
+use std::env;
+use std::fmt;
+use std::io;
+use std::iter;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{ DateTime, Utc };
+use serenity::model::id::GuildId;
+use songbird::events::context_data::{ RtpData, VoiceTick };
+use songbird::packet::{ rtp::RtpExtensionPacket, Packet, PacketSize };
+use tokio::sync::mpsc;
+use tokio::task::{ self, JoinHandle };
+
+use super::sink::{ self, Format, RecordingSink };
+use super::tracks::{ self, Speaker, Ssrcs, TICK_SAMPLES };
+
+/// How many ticks can queue up for the writer before the call waits on it, 10 seconds' worth.
+const QUEUE_TICKS: usize = 500;
+
+/// How often the files are flushed, so a crash loses little more than this much, in ticks.
+const FLUSH_TICKS: usize = 50;
+
+/// How long a part of a recording runs unless `RECORDING_PART_MINUTES` says otherwise.
+const DEFAULT_PART_DURATION: Duration = Duration::from_hours(1);
+
+/// How big a file of a part gets unless `RECORDING_PART_MB` says otherwise.
+const DEFAULT_PART_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// `WriterTask` is the task writing a recording, which returns the files it wrote.
+pub type WriterTask = JoinHandle<Result<Vec<PathBuf>, WriteError>>;
+
+/// `WriteError` is why a recording couldn't be written to the end, with the files saved until
+/// then, which were still closed properly.
+#[derive(Debug)]
+pub struct WriteError {
+    pub why: io::Error,
+    pub saved: Vec<PathBuf>,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.why)?;
+        if !self.saved.is_empty() {
+            let saved: Vec<_> = self.saved.iter().map(|path| path.display().to_string()).collect();
+            write!(f, ", what was recorded until then is in {}", saved.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// `Voice` is what someone said in a tick.
+enum Voice {
+    /// A tick's worth of samples.
+    Decoded(Vec<i16>),
+
+    /// The Opus packet Discord sent, when the call only decrypts what it receives.
+    Opus(Vec<u8>),
+}
+
+/// `Frame` is one tick of a recording: what everyone speaking in it said, by SSRC.
+pub struct Frame(Vec<(u32, Voice)>);
+
+impl Frame {
+    /// The samples of everyone speaking in the tick, leaving out undecoded packets.
+    fn decoded(&self) -> impl Iterator<Item = &[i16]> {
+        self.0.iter().filter_map(|(_, voice)| match voice {
+            Voice::Decoded(samples) => Some(samples.as_slice()),
+            Voice::Opus(_) => None,
+        })
+    }
+}
+
+impl From<&VoiceTick> for Frame {
+    fn from(tick: &VoiceTick) -> Self {
+        let speaking = tick.speaking
+            .iter()
+            .filter_map(|(ssrc, voice)| {
+                let voice = match (&voice.decoded_voice, &voice.packet) {
+                    (Some(decoded), _) => {
+                        let padded = decoded.iter().copied().chain(iter::repeat(0)).take(TICK_SAMPLES);
+                        Voice::Decoded(padded.collect())
+                    }
+                    (None, Some(packet)) => Voice::Opus(opus_payload(packet)?),
+                    (None, None) => return None,
+                };
+                Some((*ssrc, voice))
+            })
+            .collect();
+
+        Self(speaking)
+    }
+}
+
+/// The Opus packet carried by a decrypted RTP packet, past any header extension.
+fn opus_payload(packet: &RtpData) -> Option<Vec<u8>> {
+    let rtp = packet.rtp();
+    let body = rtp.payload().get(packet.payload_offset..packet.payload_end_pad)?;
+    let start = if rtp.get_extension() == 0 { 0 } else { RtpExtensionPacket::new(body)?.packet_size() };
+
+    body.get(start..).map(<[u8]>::to_vec)
+}
+
+/// `Track` is one of the files a recording is streamed into.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Track {
+    /// Everyone mixed together.
+    Mix,
+
+    /// Whoever is speaking under the SSRC.
+    Speaker(u32),
+}
+
+/// `Limits` is when a recording moves on to its next part, starting new files for every track.
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    ticks: usize,
+    bytes: u64,
+}
+
+impl Limits {
+    /// Reads the limits from `RECORDING_PART_MINUTES` and `RECORDING_PART_MB`, keeping parts
+    /// within what `format` can hold.
+    fn from_env(format: Format) -> Self {
+        let duration = env
+            ::var("RECORDING_PART_MINUTES")
+            .ok()
+            .and_then(|val| val.parse::<u64>().ok())
+            .map_or(DEFAULT_PART_DURATION, Duration::from_mins);
+        let bytes = env
+            ::var("RECORDING_PART_MB")
+            .ok()
+            .and_then(|val| val.parse::<u64>().ok())
+            .map_or(DEFAULT_PART_SIZE, |megabytes| megabytes.saturating_mul(1024 * 1024));
+
+        Self {
+            ticks: usize::try_from(duration.as_millis() / 20).unwrap_or(usize::MAX),
+            bytes: bytes.min(format.max_size()),
+        }
+    }
+}
+
+/// `Writer` streams the frames of a recording to disk, each track into its own file.
+struct Writer {
+    guild: GuildId,
+    started: DateTime<Utc>,
+    ssrcs: Ssrcs,
+    mixdown: bool,
+    format: Format,
+    limits: Limits,
+
+    /// The part being written, counting from 0.
+    part: usize,
+
+    /// How many ticks the part has so far.
+    ticks: usize,
+
+    /// The files of the part, opened once their track has something in it.
+    files: HashMap<Track, Box<dyn RecordingSink>>,
+
+    /// The names of the tracks in the part.
+    names: HashSet<String>,
+
+    /// Every file written to, in the order they were started.
+    saved: Vec<PathBuf>,
+}
+
+impl Writer {
+    fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        let full = self.files.values().any(|file| file.size() >= self.limits.bytes);
+        if self.ticks >= self.limits.ticks || full {
+            self.next_part()?;
+        }
+
+        if self.mixdown {
+            self.open(Track::Mix)?;
+        }
+        for (ssrc, _) in &frame.0 {
+            self.open(Track::Speaker(*ssrc))?;
+        }
+
+        let mix = tracks::mix(frame.decoded());
+        for (track, file) in &mut self.files {
+            match track {
+                Track::Mix => file.write(&mix)?,
+                Track::Speaker(ssrc) => {
+                    match frame.0.iter().find(|(speaking, _)| speaking == ssrc) {
+                        Some((_, Voice::Decoded(samples))) => file.write(samples)?,
+                        Some((_, Voice::Opus(packet))) => file.write_packet(packet)?,
+                        None => file.write_silence(1)?,
+                    }
+                }
+            }
+        }
+
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(FLUSH_TICKS) {
+            self.files.values_mut().try_for_each(|file| file.flush())?;
+        }
+
+        Ok(())
+    }
+
+    /// Opens the file of `track` for the part, unless it already is, padded with silence up to
+    /// where the part is.
+    fn open(&mut self, track: Track) -> io::Result<()> {
+        if self.files.contains_key(&track) {
+            return Ok(());
+        }
+
+        let mut name = match track {
+            Track::Mix => "mix".to_owned(),
+            Track::Speaker(ssrc) => {
+                let speaker = self.ssrcs.user(ssrc).map_or(Speaker::Unknown(ssrc), Speaker::User);
+                speaker.to_string()
+            }
+        };
+        // Someone who reconnected, and came back under a new SSRC
+        if let (Track::Speaker(ssrc), true) = (track, self.names.contains(&name)) {
+            name = format!("{name}_{ssrc}");
+        }
+        self.names.insert(name.clone());
+        if self.part > 0 {
+            name = format!("{name}_part{}", self.part + 1);
+        }
+
+        let path = sink::path(self.guild, self.started, &name, self.format);
+        let mut file = self.format.create(&path)?;
+        file.write_silence(self.ticks)?;
+
+        self.files.insert(track, file);
+        self.saved.push(path);
+        Ok(())
+    }
+
+    /// Finishes the files of the part and moves on to the next one, whose files are started as
+    /// their tracks get something in them.
+    fn next_part(&mut self) -> io::Result<()> {
+        self.finish_files()?;
+        self.names.clear();
+        self.part += 1;
+        self.ticks = 0;
+        Ok(())
+    }
+
+    /// Finishes every file of the part, even once one of them failed, returning the first error.
+    fn finish_files(&mut self) -> io::Result<()> {
+        self.files.drain().map(|(_, file)| file.finish()).fold(Ok(()), Result::and)
+    }
+}
+
+/// Starts the task writing the recording started in `guild` to disk as `format`, returning where
+/// to send its frames and the task, which finishes once every sender is dropped with the files
+/// it wrote.
+///
+/// The files are valid even if the bot crashes, as they're flushed every second, and are closed
+/// properly if writing fails partway through, the task then returning them with the error.
+pub fn spawn(
+    guild: GuildId,
+    started: DateTime<Utc>,
+    ssrcs: Ssrcs,
+    mixdown: bool,
+    format: Format
+) -> (mpsc::Sender<Frame>, WriterTask) {
+    let (frames, mut received) = mpsc::channel(QUEUE_TICKS);
+
+    let task = task::spawn_blocking(move || {
+        let mut writer = Writer {
+            guild,
+            started,
+            ssrcs,
+            mixdown,
+            format,
+            limits: Limits::from_env(format),
+            part: 0,
+            ticks: 0,
+            files: HashMap::new(),
+            names: HashSet::new(),
+            saved: Vec::new(),
+        };
+
+        // Stops at the first frame that can't be written, the files are finished either way
+        let written = iter::from_fn(|| received.blocking_recv()).try_for_each(|frame| writer.write(&frame));
+        let finished = writer.finish_files();
+
+        match written.and(finished) {
+            Ok(()) => Ok(writer.saved),
+            Err(why) => Err(WriteError { why, saved: writer.saved }),
+        }
+    });
+
+    (frames, task)
+}
@@ -0,0 +1,11 @@
+//! Compiles the cases in `tests/ui` against `greys_macros`: everything in `pass` has to build
+//! and run, everything in `fail` has to fail with the error in its `.stderr` file.
+//!
+//! Run with `TRYBUILD=overwrite` to update the `.stderr` files after changing an error.
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/pass/*.rs");
+    cases.compile_fail("tests/ui/fail/*.rs");
+}
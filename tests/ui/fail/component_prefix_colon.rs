@@ -0,0 +1,12 @@
+use greys_macros::component;
+
+#[component("meeting:reply")]
+fn reply(
+    _ctx: &serenity::prelude::Context,
+    _interaction: &serenity::all::ComponentInteraction,
+    _id: &crate::interactions::components::CustomId
+) -> String {
+    String::new()
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use greys_macros::user_command;
+
+#[user_command("Get IDs")]
+#[description("Gets a user's ids")]
+mod user_ids {
+    use serenity::all::User;
+
+    pub fn run(user: &User) -> String {
+        user.id.to_string()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("It Pings")]
+#[guild_only]
+#[dm_permission]
+mod ping {
+    pub fn run() -> String {
+        String::new()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Pings the bot and waits for it to answer, which shows that it is online and how long the round trip took")]
+mod ping {
+    pub fn run() -> String {
+        "Pong!".to_owned()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+mod ping {
+    pub fn run() -> String {
+        "Pong!".to_owned()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("It Pings")]
+mod ping {
+    pub fn run() {}
+}
+
+fn main() {}
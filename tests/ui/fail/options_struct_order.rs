@@ -0,0 +1,12 @@
+use greys_macros::SlashOptions;
+
+#[derive(SlashOptions)]
+pub struct MeetingOptions {
+    #[description("What the meeting is about")]
+    topic: Option<String>,
+
+    #[description("The user to meet with")]
+    user: String,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Creates a meeting")]
+#[option(string, "topic", "What the meeting is about")]
+mod create_meeting {
+    pub fn run(#[options] args: MeetingOptions) -> String {
+        String::new()
+    }
+}
+
+fn main() {}
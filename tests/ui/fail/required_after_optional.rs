@@ -0,0 +1,13 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Creates a meeting")]
+#[option(string, "topic", "What the meeting is about")]
+#[option(user, "user", "The user to meet with", required)]
+mod create_meeting {
+    pub fn run() -> String {
+        String::new()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Quotes a message")]
+mod quote {
+    use serenity::all::Message;
+
+    pub fn run(message: &Message) -> String {
+        message.content.clone()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Creates a meeting")]
+mod create_meeting {
+    #[subcommand]
+    #[description("Creates a meeting with a single user")]
+    #[guild_only]
+    mod user {
+        pub fn run() -> String {
+            String::new()
+        }
+    }
+}
+
+fn main() {}
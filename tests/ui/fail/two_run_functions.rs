@@ -0,0 +1,17 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("It Pings")]
+mod ping {
+    #[run]
+    pub fn respond() -> String {
+        "Pong!".to_owned()
+    }
+
+    #[run]
+    pub fn reply() -> String {
+        "Pong!".to_owned()
+    }
+}
+
+fn main() {}
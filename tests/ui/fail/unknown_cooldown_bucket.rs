@@ -0,0 +1,12 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("It Pings")]
+#[cooldown(server, 10)]
+mod ping {
+    pub fn run() -> String {
+        "Pong!".to_owned()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("It Pings")]
+#[description_localized("german", "Pingt")]
+mod ping {
+    pub fn run() -> String {
+        "Pong!".to_owned()
+    }
+}
+
+fn main() {}
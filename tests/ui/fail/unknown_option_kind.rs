@@ -0,0 +1,12 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Creates a meeting")]
+#[option(member, "user", "The user to meet with", required)]
+mod create_meeting {
+    pub fn run() -> String {
+        String::new()
+    }
+}
+
+fn main() {}
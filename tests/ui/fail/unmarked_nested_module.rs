@@ -0,0 +1,13 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Creates a meeting")]
+mod create_meeting {
+    mod user {
+        pub fn run() -> String {
+            String::new()
+        }
+    }
+}
+
+fn main() {}
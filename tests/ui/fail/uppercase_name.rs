@@ -0,0 +1,11 @@
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("It Pings")]
+mod Ping {
+    pub fn run() -> String {
+        "Pong!".to_owned()
+    }
+}
+
+fn main() {}
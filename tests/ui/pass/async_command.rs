@@ -0,0 +1,72 @@
+include!("../runtime.rs");
+
+use greys_macros::{ slash_command, SlashChoice, SlashOptions };
+
+#[derive(SlashChoice)]
+pub enum Format {
+    #[description("Plain text")]
+    Text,
+    Mentions,
+}
+
+#[derive(SlashOptions)]
+pub struct IdOptions {
+    #[description("The role to lookup")]
+    #[autocomplete]
+    role: String,
+
+    #[description("How to list the members")]
+    format: Option<Format>,
+}
+
+#[slash_command]
+#[description("Get user ids")]
+#[guild_only]
+#[defer]
+#[cooldown(user, 30)]
+#[default_member_permissions(MANAGE_CHANNELS)]
+mod id {
+    use serenity::all::{ AutocompleteChoice, CommandInteraction, GuildId };
+    use serenity::prelude::Context;
+    use crate::interactions::error::CommandError;
+    use crate::interactions::response::CommandResponse;
+    use super::IdOptions;
+
+    pub async fn run(
+        _ctx: &Context,
+        guild_id: GuildId,
+        #[options] args: IdOptions
+    ) -> Result<CommandResponse, CommandError> {
+        if args.role.is_empty() {
+            return Err(CommandError::user("Please provide a valid role"));
+        }
+
+        Ok(CommandResponse::from(format!("{} in {guild_id}", args.role)))
+    }
+
+    #[autocomplete(option = "role")]
+    pub async fn suggest_role(
+        _ctx: &Context,
+        _command: &CommandInteraction,
+        value: &str
+    ) -> Vec<AutocompleteChoice> {
+        vec![AutocompleteChoice::new(value, value)]
+    }
+}
+
+fn main() {
+    let id = registered("id");
+    assert_eq!(id["dm_permission"], false);
+    assert_eq!(id["default_member_permissions"], "16");
+    assert_eq!(id["options"][0]["autocomplete"], true);
+    assert_eq!(id["options"][0]["required"], true);
+    assert_eq!(id["options"][1]["required"], false);
+    assert_eq!(id["options"][1]["choices"][0]["name"], "Plain text");
+    assert_eq!(id["options"][1]["choices"][1]["value"], "mentions");
+
+    let command = interactions::registry::find(serenity::all::CommandType::ChatInput, "id")
+        .expect("command should be registered");
+    assert!(matches!(command.defer, interactions::registry::Defer::Ephemeral));
+    assert!(command.checks.guild_only);
+    assert_eq!(command.cooldowns.len(), 1);
+}
@@ -0,0 +1,59 @@
+include!("../runtime.rs");
+
+use std::time::Duration;
+
+use greys_macros::{ component, modal };
+use serenity::all::{ ComponentInteraction, ModalInteraction, UserId };
+use serenity::prelude::Context;
+
+use interactions::components::{ self, CustomId };
+use interactions::error::CommandError;
+use interactions::response::{ CommandResponse, ComponentResponse };
+
+#[component("invite")]
+fn invite(
+    _ctx: &Context,
+    _interaction: &ComponentInteraction,
+    id: &CustomId
+) -> Result<CommandResponse, CommandError> {
+    let (user, accepted): (UserId, bool) = id.payload()?;
+    Ok(CommandResponse::from(format!("<@{user}> answered {accepted}")))
+}
+
+#[modal("invite")]
+async fn invite_details(
+    _ctx: &Context,
+    _interaction: &ModalInteraction,
+    _id: &CustomId
+) -> ComponentResponse {
+    ComponentResponse::Update(CommandResponse::from("Updated"))
+}
+
+fn main() {
+    assert!(components::find_component("invite").is_some());
+    assert!(components::find_modal("invite").is_some());
+    assert!(components::find_component("unknown").is_none());
+    assert!(components::check_duplicates().is_ok());
+
+    let encoded = CustomId::new("invite")
+        .with_payload(&(UserId::new(1234), true))
+        .expect("payload should serialize")
+        .expires_in(Duration::from_secs(60))
+        .encode()
+        .expect("custom id should fit");
+    assert!(encoded.starts_with("invite:"));
+    assert!(encoded.ends_with(r#":["1234",true]"#));
+
+    let decoded = CustomId::decode(&encoded).expect("custom id should decode");
+    assert_eq!(decoded.prefix(), "invite");
+    assert!(!decoded.is_expired());
+    let payload: (UserId, bool) = decoded.payload().expect("payload should parse");
+    assert_eq!(payload, (UserId::new(1234), true));
+
+    let expired = CustomId::decode("invite:1:null").expect("custom id should decode");
+    assert!(expired.is_expired());
+    assert!(CustomId::decode("invite").is_none());
+
+    let long = CustomId::new("invite").with_payload(&"x".repeat(100)).expect("payload should serialize");
+    assert!(long.encode().is_err());
+}
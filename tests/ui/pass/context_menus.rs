@@ -0,0 +1,47 @@
+include!("../runtime.rs");
+
+use greys_macros::{ message_command, user_command };
+
+#[user_command("Get IDs")]
+#[guild_only]
+#[name_localized("de", "IDs anzeigen")]
+mod user_ids {
+    use serenity::all::{ PartialMember, User };
+
+    pub fn run(user: &User, member: Option<&PartialMember>) -> String {
+        format!("{} has {} roles", user.id, member.map_or(0, |member| member.roles.len()))
+    }
+}
+
+#[message_command("Pin or unpin")]
+#[default_member_permissions(MANAGE_MESSAGES)]
+#[dm_permission(false)]
+mod pin_message {
+    use serenity::all::Message;
+    use serenity::prelude::Context;
+    use crate::interactions::error::CommandError;
+
+    pub async fn run(_ctx: &Context, message: &Message) -> Result<String, CommandError> {
+        Ok(message.id.to_string())
+    }
+}
+
+fn main() {
+    use serenity::all::CommandType;
+
+    let user_ids = registered("Get IDs");
+    assert_eq!(user_ids["type"], 2);
+    assert_eq!(user_ids["name_localizations"]["de"], "IDs anzeigen");
+    assert_eq!(user_ids["dm_permission"], false);
+    assert!(user_ids["options"].as_array().is_some_and(Vec::is_empty));
+
+    let pin_message = registered("Pin or unpin");
+    assert_eq!(pin_message["type"], 3);
+    assert_eq!(pin_message["default_member_permissions"], "8192");
+    assert_eq!(pin_message["dm_permission"], false);
+
+    let pin = interactions::registry::find(CommandType::Message, "Pin or unpin").expect("is registered");
+    // Enforced when it's run as well, not only registered
+    assert!(pin.checks.guild_only);
+    assert!(interactions::registry::find(CommandType::ChatInput, "Pin or unpin").is_none());
+}
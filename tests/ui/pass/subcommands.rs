@@ -0,0 +1,58 @@
+include!("../runtime.rs");
+
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("Creates a meeting room")]
+#[description_localized("de", "Erstellt einen Besprechungsraum")]
+#[defer(on_budget, public)]
+mod create_meeting {
+    #[subcommand]
+    #[description("Creates a meeting with a single user")]
+    #[option(user, "user", "The user to meet with", required)]
+    mod user {
+        use serenity::model::id::UserId;
+        use crate::interactions::response::CommandResponse;
+
+        const GREETING: &str = "Meeting created for";
+
+        #[run]
+        pub fn create(user: UserId) -> CommandResponse {
+            CommandResponse::from(message(user)).ephemeral(false)
+        }
+
+        fn message(user: UserId) -> String {
+            format!("{GREETING} <@{user}>")
+        }
+    }
+
+    #[subcommand_group]
+    #[description("Meetings with whole roles")]
+    mod roles {
+        #[subcommand]
+        #[description("Creates a meeting with everyone in a role")]
+        #[option(role, "role", "The role to meet with", required)]
+        mod role {
+            use serenity::model::id::RoleId;
+
+            pub async fn run(role: RoleId) -> String {
+                format!("Meeting created for <@&{role}>")
+            }
+        }
+    }
+}
+
+fn main() {
+    let meeting = registered("create_meeting");
+    assert_eq!(meeting["description_localizations"]["de"], "Erstellt einen Besprechungsraum");
+    assert_eq!(meeting["options"][0]["name"], "user");
+    assert_eq!(meeting["options"][0]["type"], 1);
+    assert_eq!(meeting["options"][0]["options"][0]["type"], 6);
+    assert_eq!(meeting["options"][1]["name"], "roles");
+    assert_eq!(meeting["options"][1]["type"], 2);
+    assert_eq!(meeting["options"][1]["options"][0]["options"][0]["name"], "role");
+
+    let command = interactions::registry::find(serenity::all::CommandType::ChatInput, "create_meeting")
+        .expect("command should be registered");
+    assert!(matches!(command.defer, interactions::registry::Defer::OnBudget { ephemeral: false }));
+}
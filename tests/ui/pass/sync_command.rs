@@ -0,0 +1,25 @@
+include!("../runtime.rs");
+
+use greys_macros::slash_command;
+
+#[slash_command]
+#[description("It Pings")]
+#[option(string, "message", "What to reply with")]
+mod ping {
+    pub fn run(message: Option<String>) -> String {
+        message.unwrap_or_else(|| "Pong!".to_owned())
+    }
+}
+
+fn main() {
+    let ping = registered("ping");
+    assert_eq!(ping["name"], "ping");
+    assert_eq!(ping["description"], "It Pings");
+    assert_eq!(ping["options"][0]["name"], "message");
+    assert_eq!(ping["options"][0]["type"], 3);
+    assert_eq!(ping["options"][0]["required"], false);
+
+    let command = interactions::registry::find(serenity::all::CommandType::ChatInput, "ping")
+        .expect("command should be registered");
+    assert!(matches!(command.defer, interactions::registry::Defer::OnBudget { ephemeral: true }));
+}
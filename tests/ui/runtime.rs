@@ -0,0 +1,28 @@
+// The bot's own runtime for the code `#[slash_command]` generates, with stand-ins for the few
+// things it takes from `main.rs`. Pulled into the crate root of the `pass` cases with
+// `include!("../runtime.rs");`.
+
+#[path = "../../src/interactions/mod.rs"]
+mod interactions;
+
+#[path = "../../src/cooldown.rs"]
+mod cooldown;
+
+pub struct BotOwners;
+
+impl serenity::prelude::TypeMapKey for BotOwners {
+    type Value = std::collections::HashSet<serenity::model::id::UserId>;
+}
+
+pub const PREFIX: &str = "!";
+
+pub static PREFIX_GROUPS: &[&serenity::framework::standard::CommandGroup] = &[];
+
+/// Returns what `name` registers as, as Discord would receive it.
+fn registered(name: &str) -> serenity::json::Value {
+    let command = interactions::registry::commands()
+        .find(|command| command.name == name)
+        .expect("command should be registered");
+
+    serenity::json::to_value((command.register)()).expect("command should serialize")
+}